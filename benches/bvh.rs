@@ -0,0 +1,120 @@
+//! Ray queries against the bouncing spheres scene, through the linear BVH, the pointer tree
+//! it replaced and a plain list
+//!
+//! Run with `cargo bench --bench bvh`.
+
+use criterion::{ Criterion, criterion_group, criterion_main };
+use std::hint::black_box;
+use std::sync::Arc;
+use raytracing::aabb::Aabb;
+use raytracing::color::Color;
+use raytracing::hittable::{ HitRecord, Hittable };
+use raytracing::hittable::bvh::Bvh;
+use raytracing::hittable::hittable_list::HittableList;
+use raytracing::hittable::sphere::Sphere;
+use raytracing::interval::Interval;
+use raytracing::material::lambertian::Lambertian;
+use raytracing::ray::Ray;
+use raytracing::rng::Pcg32;
+use raytracing::texture::SolidColor;
+use raytracing::vec3::{ Point3, Vec3 };
+
+/// Ground, grid of small spheres, some moving, and the three large spheres
+fn bouncing_spheres(rng: &mut Pcg32) -> HittableList<'static> {
+    let mut world = HittableList::new();
+    let material = Lambertian::new(SolidColor::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Sphere::new_static(Point3::new(0.0, -1000.0, 0.0), 1000.0, material));
+    for a in -11..11 {
+        for b in -11..11 {
+            let center = Point3::new(
+                (a as f64) + 0.9 * rng.next_f64(),
+                0.2,
+                (b as f64) + 0.9 * rng.next_f64()
+            );
+            if rng.next_f64() < 0.8 {
+                let center2 = center + Vec3::new(0.0, rng.float(0.0, 0.5), 0.0);
+                world.add(Sphere::new_moving(center, center2, 0.2, material));
+            } else {
+                world.add(Sphere::new_static(center, 0.2, material));
+            }
+        }
+    }
+    world.add(Sphere::new_static(Point3::new(0.0, 1.0, 0.0), 1.0, material));
+    world.add(Sphere::new_static(Point3::new(-4.0, 1.0, 0.0), 1.0, material));
+    world.add(Sphere::new_static(Point3::new(4.0, 1.0, 0.0), 1.0, material));
+    world
+}
+
+/// Camera rays from the scene's viewpoint through its field of view
+fn camera_rays(rng: &mut Pcg32, n: usize) -> Vec<Ray> {
+    let lookfrom = Point3::new(13.0, 2.0, 3.0);
+    (0..n)
+        .map(|_| {
+            let target = Point3::new(rng.float(-8.0, 8.0), rng.float(-1.0, 3.0), rng.float(-5.0, 5.0));
+            Ray::new(lookfrom, target - lookfrom, rng.next_f64())
+        })
+        .collect()
+}
+
+/// Baseline pointer tree, as BvhNode was before the linear BVH: one object per leaf, the
+/// object duplicated when a node has only one, split at the median along a random axis
+struct PointerBvh {
+    left_node: Arc<dyn Hittable>,
+    right_node: Arc<dyn Hittable>,
+    bounding_box: Aabb,
+}
+
+impl PointerBvh {
+    fn from_list(hlist: &mut [Arc<dyn Hittable>], rng: &mut Pcg32) -> Self {
+        let axis = rng.int(0, 2);
+        let (left_node, right_node): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match hlist.len() {
+            1 => (hlist[0].clone(), hlist[0].clone()),
+            2 => (hlist[0].clone(), hlist[1].clone()),
+            n => {
+                hlist.sort_by(|a, b| a.bounding_box().axis(axis).min.total_cmp(&b.bounding_box().axis(axis).min));
+                let (left, right) = hlist.split_at_mut(n / 2);
+                (Arc::new(PointerBvh::from_list(left, rng)), Arc::new(PointerBvh::from_list(right, rng)))
+            }
+        };
+        let bounding_box = Aabb::from_boxes(left_node.bounding_box(), right_node.bounding_box());
+        PointerBvh { left_node, right_node, bounding_box }
+    }
+}
+
+impl Hittable for PointerBvh {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        self.bounding_box.hit(r, ray_t)?;
+        let hit_left = self.left_node.hit(r, ray_t);
+        let right_max = hit_left.as_ref().map_or(ray_t.max, |rec| rec.t);
+        let hit_right = self.right_node.hit(r, Interval::new(ray_t.min, right_max));
+        hit_right.or(hit_left)
+    }
+    fn bounding_box(&self) -> &Aabb {
+        &self.bounding_box
+    }
+}
+
+fn trace(world: &impl Hittable, rays: &[Ray]) -> usize {
+    rays.iter()
+        .filter(|r| world.hit(r, Interval::new(0.001, f64::INFINITY)).is_some())
+        .count()
+}
+
+fn bench_bouncing_spheres(c: &mut Criterion) {
+    let mut rng = Pcg32::new(0, 0);
+    let world = bouncing_spheres(&mut rng);
+    let mut objects: Vec<Arc<dyn Hittable>> = world.objects.clone();
+    let bvh = Bvh::from_list(&mut objects);
+    let pointer_bvh = PointerBvh::from_list(&mut world.objects.clone(), &mut rng);
+    let rays = camera_rays(&mut rng, 10_000);
+    assert_eq!(trace(&bvh, &rays), trace(&pointer_bvh, &rays));
+
+    let mut group = c.benchmark_group("bouncing_spheres");
+    group.bench_function("list", |b| b.iter(|| trace(black_box(&world), black_box(&rays))));
+    group.bench_function("pointer_bvh", |b| b.iter(|| trace(black_box(&pointer_bvh), black_box(&rays))));
+    group.bench_function("bvh", |b| b.iter(|| trace(black_box(&bvh), black_box(&rays))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_bouncing_spheres);
+criterion_main!(benches);
@@ -1,7 +1,7 @@
 //! Implements Axis-aligned bounding boxes
 
 use crate::interval::Interval;
-use crate::vec3::Point3;
+use crate::vec3::{ Point3, Vec3 };
 use crate::ray::Ray;
 
 #[derive(Debug, Clone)]
@@ -56,15 +56,45 @@ impl Aabb {
             _ => &self.z,
         }
     }
+    /// Center point of the box
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            0.5 * (self.x.min + self.x.max),
+            0.5 * (self.y.min + self.y.max),
+            0.5 * (self.z.min + self.z.max)
+        )
+    }
+    /// Total area of the six faces
+    pub fn surface_area(&self) -> f64 {
+        let (dx, dy, dz) = (self.x.size(), self.y.size(), self.z.size());
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
     /// return interval of longest axis
     pub fn longest_axis(&self) -> i32 {
         if self.x > self.y && self.x > self.z {
-            return 0;
+            0
         } else if self.y > self.z {
-            return 1;
+            1
         } else {
-            return 2;
+            2
+        }
+    }
+    /// Slab test like `hit` for traversals checking many boxes against one ray, which compute
+    /// the reciprocal of its direction once
+    pub fn hit_inverse(&self, origin: Point3, inv_direction: Vec3, ray_t: Interval) -> bool {
+        let mut t_min = ray_t.min;
+        let mut t_max = ray_t.max;
+        for axis in 0..3 {
+            let ax = self.axis(axis);
+            let t0 = (ax.min - origin.axis(axis)) * inv_direction.axis(axis);
+            let t1 = (ax.max - origin.axis(axis)) * inv_direction.axis(axis);
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+            if t_max <= t_min {
+                return false;
+            }
         }
+        true
     }
     /// Check whether bounding box is hit by ray r
     pub fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interval> {
@@ -80,18 +110,19 @@ impl Aabb {
             let t0 = (ax.min - ray_orig.axis(axis)) * adinv;
             let t1 = (ax.max - ray_orig.axis(axis)) * adinv;
 
+            // narrow the running interval, not the original, so slabs must overlap
             if t0 < t1 {
-                if t0 > ray_t.min {
+                if t0 > ray_t_min {
                     ray_t_min = t0;
                 }
-                if t1 < ray_t.max {
+                if t1 < ray_t_max {
                     ray_t_max = t1;
                 }
             } else {
-                if t1 > ray_t.min {
+                if t1 > ray_t_min {
                     ray_t_min = t1;
                 }
-                if t0 < ray_t.max {
+                if t0 < ray_t_max {
                     ray_t_max = t0;
                 }
             }
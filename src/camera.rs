@@ -1,12 +1,26 @@
 //! Camera code
 
 use crate::ray::Ray;
-use crate::color::{ Color, write_color };
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
 use crate::interval::Interval;
-use crate::hittable::Hittable;
+use crate::hittable::{ Hittable, HitRecord };
+use crate::hittable::hittable_list::HittableList;
+use crate::material::Scatter;
+use crate::pdf::{ HittablePdf, Pdf, power_heuristic };
 use crate::vec3::{ Point3, Vec3 };
-use crate::random_float;
+use crate::rng::Pcg32;
 use crate::degrees_to_radians;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::io::{ IsTerminal, Write };
+use std::sync::mpsc;
+use std::thread;
+use std::time::{ Duration, Instant };
+
+/// Width and height in pixels of the square tiles handed to render threads
+const TILE_SIZE: i32 = 16;
+/// Width in characters of the progress bar
+const PROGRESS_BAR_WIDTH: usize = 30;
 
 pub struct CameraArgs {
     pub aspect_ratio: f64, // Ratio of image widht over height
@@ -20,8 +34,13 @@ pub struct CameraArgs {
     pub vup: Vec3, // Camera-relative up direction
     pub defocus_angle: f64, // Variation angle of rays through each pixel
     pub focus_dist: f64, // Distance from camera lookfrom point to plane of perfect focus
+    pub threads: usize, // Number of render threads, defaults to available parallelism
+    pub russian_roulette_depth: i32, // Bounces before paths may be terminated randomly
+    pub progress: bool, // Show render progress on stderr when it is a terminal
+    pub seed: u64, // Seed for all random sampling, the same seed gives the same image
 }
 impl CameraArgs {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         aspect_ratio: f64,
         image_width: i32,
@@ -47,6 +66,10 @@ impl CameraArgs {
             vup,
             defocus_angle,
             focus_dist,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            russian_roulette_depth: 3,
+            progress: true,
+            seed: 0,
         }
     }
 
@@ -119,6 +142,7 @@ pub struct Camera {
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         args: CameraArgs,
         image_height: i32,
@@ -142,75 +166,347 @@ impl Camera {
             defocus_disk_v,
         }
     }
-    /// Render hittable object on camera
-    pub fn render(&self, world: impl Hittable) {
-        let mut out = std::io::stdout();
+    /// Render hittable object on camera into a framebuffer
+    ///
+    /// `lights` holds every emissive object in the world, which are sampled directly at each
+    /// diffuse bounce. Emitters missing from `lights` are only seen directly or through
+    /// specular bounces. Pass an empty list to rely on bounces hitting lights by chance.
+    ///
+    /// The image is split into tiles which are pulled off a shared counter by a pool of
+    /// `threads` workers. Finished tiles are copied into place by position, and each pixel
+    /// draws its samples from its own random stream of `seed`, so the output does not depend
+    /// on which thread rendered which tile.
+    pub fn render(&self, world: impl Hittable, lights: &HittableList) -> Framebuffer {
+        let tiles = self.tiles();
+        let mut framebuffer = Framebuffer::new(
+            self.args.image_width as usize,
+            self.image_height as usize
+        );
+        let samples = self.args.samples_per_pixel as u32;
+
+        let mut progress = Progress::new(tiles.len(), self.args.progress);
+        let next_tile = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|s| {
+            for _ in 0..self.args.threads.max(1) {
+                let sender = sender.clone();
+                let (next_tile, tiles, world) = (&next_tile, &tiles, &world);
+                s.spawn(move || {
+                    loop {
+                        let idx = next_tile.fetch_add(1, Ordering::Relaxed);
+                        let Some(tile) = tiles.get(idx) else {
+                            break;
+                        };
+                        if sender.send((idx, self.render_tile(tile, world, lights))).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
 
-        // Render image
-        println!("P3\n{} {}\n255", self.args.image_width, self.image_height);
+            // Collect finished tiles on this thread while the workers render
+            for (idx, tile_pixels) in receiver.iter() {
+                progress.tile_done();
+                let tile = &tiles[idx];
+                let mut tile_pixels = tile_pixels.into_iter();
+                for j in tile.y0..tile.y1 {
+                    for i in tile.x0..tile.x1 {
+                        let pixel_color = tile_pixels.next().unwrap();
+                        framebuffer.set_pixel(i as usize, j as usize, pixel_color, samples);
+                    }
+                }
+            }
+        });
+        progress.finish();
+        framebuffer
+    }
 
-        for j in 0..self.image_height {
-            eprint!("\rScanlines remaining {} ", self.image_height - j);
-            for i in 0..self.args.image_width {
+    /// Split image into tiles of at most TILE_SIZE x TILE_SIZE pixels in scanline order
+    fn tiles(&self) -> Vec<Tile> {
+        let mut tiles = Vec::new();
+        for y0 in (0..self.image_height).step_by(TILE_SIZE as usize) {
+            for x0 in (0..self.args.image_width).step_by(TILE_SIZE as usize) {
+                tiles.push(Tile {
+                    x0,
+                    y0,
+                    x1: (x0 + TILE_SIZE).min(self.args.image_width),
+                    y1: (y0 + TILE_SIZE).min(self.image_height),
+                });
+            }
+        }
+        tiles
+    }
+
+    /// Render every pixel of a tile, returned row by row
+    fn render_tile(&self, tile: &Tile, world: &impl Hittable, lights: &HittableList) -> Vec<Color> {
+        let mut tile_pixels = Vec::with_capacity(((tile.x1 - tile.x0) * (tile.y1 - tile.y0)) as usize);
+        for j in tile.y0..tile.y1 {
+            for i in tile.x0..tile.x1 {
+                let stream = (j as u64) * (self.args.image_width as u64) + (i as u64);
+                let mut rng = Pcg32::new(self.args.seed, stream);
                 let mut pixel_color = Color::new(0.0, 0.0, 0.0);
                 for _sample in 0..self.args.samples_per_pixel {
-                    let r = self.get_ray(i, j);
-                    pixel_color += self.ray_color(&r, self.args.max_depth, &world);
+                    let r = self.get_ray(i, j, &mut rng);
+                    pixel_color += self.ray_color(&r, world, lights, &mut rng);
                 }
-                write_color(&mut out, self.pixel_samples_scale * pixel_color).unwrap();
+                tile_pixels.push(self.pixel_samples_scale * pixel_color);
             }
         }
-        eprint!("\rDone                                                   \n");
+        tile_pixels
     }
     /// Generate ray with direction toward random point on unit square centered on given pixel
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
-        let offset = sample_square();
+    fn get_ray(&self, i: i32, j: i32, rng: &mut Pcg32) -> Ray {
+        let offset = sample_square(rng);
         let pixel_sample =
             self.pixel00_loc +
             ((i as f64) + offset.x) * self.pixel_delta_u +
             ((j as f64) + offset.y) * self.pixel_delta_v;
         let ray_origin = match self.args.defocus_angle <= 0.0 {
             true => self.center,
-            false => self.defocus_disk_sample(),
+            false => self.defocus_disk_sample(rng),
         };
         let ray_direction = pixel_sample - ray_origin;
-        let ray_time = random_float(0.0, 1.0);
+        let ray_time = rng.next_f64();
         Ray::new(ray_origin, ray_direction, ray_time)
     }
 
     /// Returns a random point in the camera defocus disk.
-    fn defocus_disk_sample(&self) -> Point3 {
-        let p = Vec3::random_in_unit_disk();
+    fn defocus_disk_sample(&self, rng: &mut Pcg32) -> Point3 {
+        let p = Vec3::random_in_unit_disk(rng);
         self.center + p.x * self.defocus_disk_u + p.y * self.defocus_disk_v
     }
 
-    fn ray_color(&self, r: &Ray, depth: i32, world: &impl Hittable) -> Color {
-        // exits after max depth exceeded
-        if depth <= 0 {
+    /// Radiance arriving along ray r, following its path for at most `max_depth` bounces.
+    ///
+    /// At each diffuse bounce lights are sampled directly as well as continuing the path in a
+    /// direction drawn from the material. Emission the path then hits is weighted against the
+    /// chance of light sampling having found it, combining both strategies with the power
+    /// heuristic.
+    ///
+    /// After `russian_roulette_depth` bounces paths are randomly terminated with probability
+    /// based on their remaining throughput, and survivors are scaled up to keep the result
+    /// unbiased.
+    fn ray_color(&self, r: &Ray, world: &impl Hittable, lights: &HittableList, rng: &mut Pcg32) -> Color {
+        let sample_lights = !lights.objects.is_empty();
+        let mut radiance = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = r.clone();
+        // density the material drew the current ray with, when lights were also sampled
+        let mut bsdf_pdf: Option<f64> = None;
+
+        for depth in 0..self.args.max_depth {
+            let Some(rec) = world.sample_hit(&ray, Interval::new(0.001, f64::INFINITY), rng) else {
+                // defines background if nothing hit
+                radiance += throughput * self.args.background;
+                break;
+            };
+            let mut color_from_emission = rec.material.emit(&ray, &rec);
+            if let Some(bsdf_pdf) = bsdf_pdf {
+                let light_pdf = lights.pdf_value(ray.origin, ray.direction);
+                color_from_emission = power_heuristic(bsdf_pdf, light_pdf) * color_from_emission;
+            }
+            radiance += throughput * color_from_emission;
+
+            // path ends if material doesn't scatter
+            let Some(srec) = rec.material.scatter(&ray, &rec, rng) else {
+                break;
+            };
+            match srec.scatter {
+                Scatter::Specular(scattered) => {
+                    throughput *= srec.attenuation;
+                    bsdf_pdf = None;
+                    ray = scattered;
+                }
+                Scatter::Diffuse(pdf) => {
+                    if sample_lights {
+                        let color_from_lights =
+                            self.sample_lights(&ray, &rec, srec.attenuation, pdf.as_ref(), world, lights, rng);
+                        radiance += throughput * color_from_lights;
+                    }
+
+                    let scattered = Ray::new(rec.p, pdf.generate(rng), ray.time);
+                    let pdf_value = pdf.value(scattered.direction);
+                    let scattering_pdf = rec.material.scattering_pdf(&ray, &rec, &scattered);
+                    if pdf_value <= 0.0 || scattering_pdf <= 0.0 {
+                        break;
+                    }
+                    throughput *= (scattering_pdf / pdf_value) * srec.attenuation;
+                    bsdf_pdf = sample_lights.then_some(pdf_value);
+                    ray = scattered;
+                }
+            }
+
+            if depth + 1 >= self.args.russian_roulette_depth {
+                let survival = throughput.max_component().min(1.0);
+                if rng.next_f64() >= survival {
+                    break;
+                }
+                throughput = (1.0 / survival) * throughput;
+            }
+        }
+        radiance
+    }
+
+    /// Light reaching hit point directly from one sampled point on the lights, weighted by the
+    /// material's attenuation and scattering density in that direction. The sample is weighted
+    /// against the chance of the material's own `bsdf_pdf` having drawn the same direction.
+    #[allow(clippy::too_many_arguments)]
+    fn sample_lights(
+        &self,
+        r: &Ray,
+        rec: &HitRecord,
+        attenuation: Color,
+        bsdf_pdf: &dyn Pdf,
+        world: &impl Hittable,
+        lights: &HittableList,
+        rng: &mut Pcg32
+    ) -> Color {
+        let light_pdf = HittablePdf::new(lights, rec.p);
+        let shadow_ray = Ray::new(rec.p, light_pdf.generate(rng), r.time);
+        let pdf_value = light_pdf.value(shadow_ray.direction);
+        let scattering_pdf = rec.material.scattering_pdf(r, rec, &shadow_ray);
+        if pdf_value <= 0.0 || scattering_pdf <= 0.0 {
             return Color::new(0.0, 0.0, 0.0);
         }
-        if let Some(rec) = world.hit(r, Interval::new(0.001, f64::INFINITY)) {
-            let color_from_emission = rec.material.emit(rec.u, rec.v, rec.p);
-            match rec.material.scatter(r, &rec) {
-                Some((scattered, attenuation)) => {
-                    // if hit return color from scattering + color from emission
-                    let color_from_scatter =
-                        attenuation * self.ray_color(&scattered, depth - 1, world);
-                    return color_from_scatter + color_from_emission;
+
+        // find the sampled light, then check nothing blocks the way to it and dim it by any media
+        // in between
+        match lights.hit(&shadow_ray, Interval::new(0.001, f64::INFINITY)) {
+            Some(light_rec) => {
+                let segment = Interval::new(0.001, light_rec.t - 0.001);
+                if world.occluded(&shadow_ray, segment) {
+                    return Color::new(0.0, 0.0, 0.0);
                 }
-                None => {
-                    // if nothing is hit return emitted color
-                    return color_from_emission;
+                let transmittance = world.transmittance(&shadow_ray, segment, rng);
+                if transmittance <= 0.0 {
+                    return Color::new(0.0, 0.0, 0.0);
                 }
+                let emitted = light_rec.material.emit(&shadow_ray, &light_rec);
+                let weight = power_heuristic(pdf_value, bsdf_pdf.value(shadow_ray.direction));
+                (weight * transmittance * scattering_pdf / pdf_value) * (attenuation * emitted)
             }
+            None => Color::new(0.0, 0.0, 0.0),
         }
+    }
+}
+
+/// Rectangular block of pixels from (x0, y0) inclusive to (x1, y1) exclusive
+struct Tile {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
 
-        // defines background if nothing hit
-        self.args.background
+/// Progress bar with elapsed and estimated remaining time, redrawn on stderr as tiles finish
+struct Progress {
+    total: usize,
+    done: usize,
+    start: Instant,
+    enabled: bool, // false when disabled or stderr is not a terminal
+}
+
+impl Progress {
+    fn new(total: usize, enabled: bool) -> Self {
+        Progress { total, done: 0, start: Instant::now(), enabled: enabled && std::io::stderr().is_terminal() }
     }
+    fn tile_done(&mut self) {
+        self.done += 1;
+        if !self.enabled {
+            return;
+        }
+        let fraction = (self.done as f64) / (self.total as f64);
+        let filled = ((fraction * (PROGRESS_BAR_WIDTH as f64)) as usize).min(PROGRESS_BAR_WIDTH);
+        let elapsed = self.start.elapsed();
+        let remaining = elapsed.mul_f64((1.0 - fraction) / fraction);
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r[{}{}] {:5.1}% {} elapsed, {} left ",
+            "#".repeat(filled),
+            " ".repeat(PROGRESS_BAR_WIDTH - filled),
+            100.0 * fraction,
+            format_duration(elapsed),
+            format_duration(remaining)
+        );
+        let _ = stderr.flush();
+    }
+    fn finish(&self) {
+        if self.enabled {
+            eprintln!("\r\x1b[KRendered {} tiles in {}", self.total, format_duration(self.start.elapsed()));
+        }
+    }
+}
+
+/// Duration as m:ss
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
 }
 
 /// Create vector on the unit square centered on 0
-fn sample_square() -> Vec3 {
-    Vec3::new(random_float(-0.5, 0.5), random_float(-0.5, 0.5), 0.0)
+fn sample_square(rng: &mut Pcg32) -> Vec3 {
+    Vec3::new(rng.float(-0.5, 0.5), rng.float(-0.5, 0.5), 0.0)
+}
+
+#[test]
+fn test_tiles_cover_image() {
+    let camera = CameraArgs::new(
+        1.5,
+        37,
+        1,
+        1,
+        Color::new(0.0, 0.0, 0.0),
+        90.0,
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(0.0, 0.0, -1.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        0.0,
+        1.0
+    ).initialize();
+    let mut covered = vec![0; 37 * 24];
+    for tile in camera.tiles() {
+        for j in tile.y0..tile.y1 {
+            for i in tile.x0..tile.x1 {
+                covered[(j * 37 + i) as usize] += 1;
+            }
+        }
+    }
+    assert!(covered.iter().all(|&c| c == 1));
+}
+
+#[test]
+fn test_seeded_render_is_deterministic() {
+    use crate::hittable::sphere::Sphere;
+    use crate::material::lambertian::Lambertian;
+    use crate::texture::SolidColor;
+    let render = |seed: u64, threads: usize| {
+        let mut args = CameraArgs::new(
+            1.0,
+            20,
+            4,
+            10,
+            Color::new(0.7, 0.8, 1.0),
+            90.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            1.0
+        );
+        (args.seed, args.threads, args.progress) = (seed, threads, false);
+        let mut world = HittableList::new();
+        world.add(
+            Sphere::new_static(
+                Point3::new(0.0, 0.0, -1.0),
+                0.5,
+                Lambertian::new(SolidColor::new(Color::new(0.5, 0.5, 0.5)))
+            )
+        );
+        args.initialize().render(world, &HittableList::new()).pixels().to_vec()
+    };
+    let single = render(3, 1);
+    assert_eq!(single, render(3, 4));
+    assert_ne!(single, render(4, 1));
 }
@@ -4,7 +4,7 @@ use anyhow::Result;
 use std::io::Write;
 use std::ops;
 use crate::vec3::Vec3;
-use crate::interval::Interval;
+use crate::tonemap::DisplayPipeline;
 
 /// Defines r,g,b color object
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -16,29 +16,26 @@ pub struct Color {
 
 impl Color {
     pub fn new(r: f64, g: f64, b: f64) -> Self {
-        Color { r: r, g: g, b: b }
+        Color { r, g, b }
     }
-}
-
-/// Translate linear color value to gamma corrected value
-fn linear_to_gamma(linear_component: f64) -> f64 {
-    if linear_component > 0.0 {
-        return linear_component.sqrt();
+    /// Largest of the r, g, b components
+    pub fn max_component(&self) -> f64 {
+        self.r.max(self.g).max(self.b)
     }
-    0.0
 }
 
-/// Write color value to specified output stream
-pub fn write_color(mut out: impl Write, pixel_color: Color) -> Result<()> {
-    let r = linear_to_gamma(pixel_color.r);
-    let g = linear_to_gamma(pixel_color.g);
-    let b = linear_to_gamma(pixel_color.b);
+/// Write color value to specified output stream using the default display pipeline
+pub fn write_color(out: impl Write, pixel_color: Color) -> Result<()> {
+    write_display_color(out, pixel_color, &DisplayPipeline::default())
+}
 
-    // Translate [0, 1] components to [0, 255]
-    let intensity = Interval::new(0.0, 0.999);
-    let rbyte = (256.0 * intensity.itv_clamp(r)) as usize;
-    let gbyte = (256.0 * intensity.itv_clamp(g)) as usize;
-    let bbyte = (256.0 * intensity.itv_clamp(b)) as usize;
+/// Write color value mapped through a display pipeline to specified output stream
+pub fn write_display_color(
+    mut out: impl Write,
+    pixel_color: Color,
+    display: &DisplayPipeline
+) -> Result<()> {
+    let [rbyte, gbyte, bbyte] = display.to_bytes(pixel_color);
     writeln!(out, "{rbyte} {gbyte} {bbyte}")?;
     Ok(())
 }
@@ -58,17 +55,96 @@ impl ops::AddAssign<Color> for Color {
     }
 }
 
+impl ops::Sub<Color> for Color {
+    type Output = Color;
+    fn sub(self, rhs: Color) -> Color {
+        Color::new(self.r - rhs.r, self.g - rhs.g, self.b - rhs.b)
+    }
+}
+
+impl ops::SubAssign<Color> for Color {
+    fn sub_assign(&mut self, rhs: Color) {
+        *self = *self - rhs;
+    }
+}
+
 impl ops::Mul<Color> for Color {
     type Output = Color;
     fn mul(self, rhs: Color) -> Color {
         Color::new(rhs.r * self.r, rhs.g * self.g, rhs.b * self.b)
     }
 }
+
+impl ops::MulAssign<Color> for Color {
+    fn mul_assign(&mut self, rhs: Color) {
+        *self = *self * rhs;
+    }
+}
+
+impl ops::Mul<f64> for Color {
+    type Output = Color;
+    fn mul(self, rhs: f64) -> Color {
+        rhs * self
+    }
+}
+
+impl ops::MulAssign<f64> for Color {
+    fn mul_assign(&mut self, rhs: f64) {
+        *self = rhs * *self;
+    }
+}
+
+impl ops::Div<f64> for Color {
+    type Output = Color;
+    fn div(self, rhs: f64) -> Color {
+        Color::new(self.r / rhs, self.g / rhs, self.b / rhs)
+    }
+}
+
+impl ops::DivAssign<f64> for Color {
+    fn div_assign(&mut self, rhs: f64) {
+        *self = *self / rhs;
+    }
+}
+
+impl ops::Index<usize> for Color {
+    type Output = f64;
+    fn index(&self, i: usize) -> &f64 {
+        match i {
+            0 => &self.r,
+            1 => &self.g,
+            2 => &self.b,
+            _ => panic!("Color index {i} out of range"),
+        }
+    }
+}
+
+impl ops::IndexMut<usize> for Color {
+    fn index_mut(&mut self, i: usize) -> &mut f64 {
+        match i {
+            0 => &mut self.r,
+            1 => &mut self.g,
+            2 => &mut self.b,
+            _ => panic!("Color index {i} out of range"),
+        }
+    }
+}
+
+impl std::iter::Sum for Color {
+    fn sum<I: Iterator<Item = Color>>(iter: I) -> Color {
+        iter.fold(Color::new(0.0, 0.0, 0.0), |acc, c| acc + c)
+    }
+}
 impl From<Vec3> for Color {
     fn from(v: Vec3) -> Color {
         Color::new(v.x, v.y, v.z)
     }
 }
+impl From<Color> for Vec3 {
+    fn from(c: Color) -> Vec3 {
+        Vec3::new(c.r, c.g, c.b)
+    }
+}
 
 impl ops::Mul<Color> for f64 {
     type Output = Color;
@@ -89,3 +165,32 @@ fn test_add_assign() {
     c += c3;
     assert_eq!(c, result);
 }
+
+#[test]
+fn test_color_ops() {
+    let a = Color::new(1.0, 2.0, 4.0);
+    let b = Color::new(0.5, 0.5, 2.0);
+    assert_eq!(a + b, Color::new(1.5, 2.5, 6.0));
+    assert_eq!(a - b, Color::new(0.5, 1.5, 2.0));
+    assert_eq!(a * b, Color::new(0.5, 1.0, 8.0));
+    assert_eq!(a * 2.0, 2.0 * a);
+    assert_eq!(a / 2.0, Color::new(0.5, 1.0, 2.0));
+    assert_eq!([a, b].into_iter().sum::<Color>(), a + b);
+    assert_eq!(Color::from(Vec3::from(a)), a);
+    assert_eq!(a.max_component(), 4.0);
+}
+
+#[test]
+fn test_color_assign_ops() {
+    let mut c = Color::new(1.0, 2.0, 4.0);
+    c -= Color::new(1.0, 1.0, 1.0);
+    assert_eq!(c, Color::new(0.0, 1.0, 3.0));
+    c *= Color::new(2.0, 2.0, 0.5);
+    assert_eq!(c, Color::new(0.0, 2.0, 1.5));
+    c *= 2.0;
+    assert_eq!(c, Color::new(0.0, 4.0, 3.0));
+    c /= 4.0;
+    assert_eq!(c, Color::new(0.0, 1.0, 0.75));
+    c[0] = 0.5;
+    assert_eq!([c[0], c[1], c[2]], [0.5, 1.0, 0.75]);
+}
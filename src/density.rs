@@ -0,0 +1,45 @@
+//! Density fields describing how thick a heterogeneous medium is at each point
+
+pub mod noise;
+pub mod voxel;
+
+use crate::vec3::Point3;
+use std::sync::Arc;
+
+/// Scalar density at points in space. Media sample these with delta tracking, which needs
+/// an upper bound on the density to propose collisions.
+pub trait DensityField: Send + Sync {
+    /// Density at p, between 0 and `max_density`
+    fn density(&self, p: Point3) -> f64;
+    /// Upper bound of the density anywhere in the field
+    fn max_density(&self) -> f64;
+}
+
+impl<D: DensityField + ?Sized> DensityField for Arc<D> {
+    fn density(&self, p: Point3) -> f64 {
+        (**self).density(p)
+    }
+    fn max_density(&self) -> f64 {
+        (**self).max_density()
+    }
+}
+
+/// Density given by a function of position, clamped to [0, max_density]
+#[derive(Clone)]
+pub struct ProceduralDensity<F: Fn(Point3) -> f64 + Send + Sync> {
+    function: F,
+    max_density: f64,
+}
+impl<F: Fn(Point3) -> f64 + Send + Sync> ProceduralDensity<F> {
+    pub fn new(function: F, max_density: f64) -> Self {
+        ProceduralDensity { function, max_density }
+    }
+}
+impl<F: Fn(Point3) -> f64 + Send + Sync> DensityField for ProceduralDensity<F> {
+    fn density(&self, p: Point3) -> f64 {
+        (self.function)(p).clamp(0.0, self.max_density)
+    }
+    fn max_density(&self) -> f64 {
+        self.max_density
+    }
+}
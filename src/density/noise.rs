@@ -0,0 +1,31 @@
+//! Density from turbulent Perlin noise, for clouds and smoke
+
+use crate::density::DensityField;
+use crate::vec3::Point3;
+use crate::perlin::PerlinGenerator;
+
+const N: usize = 256;
+
+/// Turbulence of a `PerlinGenerator`, clipped to 1 and scaled to max_density
+#[derive(Clone)]
+pub struct NoiseDensity {
+    noise_generator: PerlinGenerator<N>,
+    scale: f64, // spatial frequency of the noise
+    depth: i32, // octaves of turbulence
+    max_density: f64,
+}
+impl NoiseDensity {
+    pub fn new(scale: f64, depth: i32, max_density: f64) -> Self {
+        let noise_generator = PerlinGenerator::<N>::init();
+        NoiseDensity { noise_generator, scale, depth, max_density }
+    }
+}
+
+impl DensityField for NoiseDensity {
+    fn density(&self, p: Point3) -> f64 {
+        self.max_density * self.noise_generator.turb(self.scale * p, self.depth).min(1.0)
+    }
+    fn max_density(&self) -> f64 {
+        self.max_density
+    }
+}
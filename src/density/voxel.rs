@@ -0,0 +1,108 @@
+//! Density sampled on a dense voxel grid
+
+use anyhow::{ Context, Result, ensure };
+use std::path::Path;
+use crate::density::DensityField;
+use crate::vec3::Point3;
+use crate::aabb::Aabb;
+
+/// Densities at the centres of a regular grid of voxels filling bounds, interpolated
+/// trilinearly between centres. Points outside bounds have no density.
+#[derive(Debug, Clone)]
+pub struct VoxelGrid {
+    dimensions: [usize; 3], // voxels along x, y and z
+    values: Vec<f64>, // x varies fastest, then y, then z
+    bounds: Aabb,
+    max_density: f64,
+}
+
+impl VoxelGrid {
+    /// Negative values are clamped to 0
+    pub fn new(dimensions: [usize; 3], values: Vec<f64>, bounds: Aabb) -> Result<Self> {
+        let [nx, ny, nz] = dimensions;
+        ensure!(nx > 0 && ny > 0 && nz > 0, "voxel grid dimensions must be positive");
+        ensure!(
+            values.len() == nx * ny * nz,
+            "voxel grid of {nx}x{ny}x{nz} needs {} values, got {}",
+            nx * ny * nz,
+            values.len()
+        );
+        let values: Vec<f64> = values.into_iter().map(|v| v.max(0.0)).collect();
+        let max_density = values.iter().copied().fold(0.0, f64::max);
+        Ok(VoxelGrid { dimensions, values, bounds, max_density })
+    }
+
+    /// Load a headerless file of little endian f32 values, x varying fastest
+    pub fn load_raw(path: impl AsRef<Path>, dimensions: [usize; 3], bounds: Aabb) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_raw(&data, dimensions, bounds).with_context(|| format!("failed to load {}", path.display()))
+    }
+
+    /// Parse the contents of a raw f32 voxel file
+    pub fn from_raw(data: &[u8], dimensions: [usize; 3], bounds: Aabb) -> Result<Self> {
+        ensure!(data.len().is_multiple_of(4), "raw voxel data length {} is not a multiple of 4 bytes", data.len());
+        let values = data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+            .collect();
+        VoxelGrid::new(dimensions, values, bounds)
+    }
+
+    fn value(&self, x: usize, y: usize, z: usize) -> f64 {
+        let [nx, ny, _] = self.dimensions;
+        self.values[x + nx * (y + ny * z)]
+    }
+}
+
+impl DensityField for VoxelGrid {
+    fn density(&self, p: Point3) -> f64 {
+        // continuous voxel coordinates with centres at whole numbers, clamped to the edge voxels
+        let mut cell = [0usize; 3];
+        let mut frac = [0.0; 3];
+        for axis in 0..3 {
+            let itv = self.bounds.axis(axis as i32);
+            let coord = p[axis];
+            if coord < itv.min || coord > itv.max {
+                return 0.0;
+            }
+            let n = self.dimensions[axis];
+            let x = ((coord - itv.min) / (itv.max - itv.min) * (n as f64) - 0.5).clamp(0.0, (n - 1) as f64);
+            cell[axis] = (x.floor() as usize).min(n.saturating_sub(2));
+            frac[axis] = if n > 1 { x - cell[axis] as f64 } else { 0.0 };
+        }
+        let next = |axis: usize| (cell[axis] + 1).min(self.dimensions[axis] - 1);
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let pick = |axis: usize| match corner & (1 << axis) == 0 {
+                true => (cell[axis], 1.0 - frac[axis]),
+                false => (next(axis), frac[axis]),
+            };
+            let ((x, wx), (y, wy), (z, wz)) = (pick(0), pick(1), pick(2));
+            density += wx * wy * wz * self.value(x, y, z);
+        }
+        density
+    }
+    fn max_density(&self) -> f64 {
+        self.max_density
+    }
+}
+
+#[test]
+fn test_voxel_grid() {
+    use crate::vec3::Vec3;
+    let bounds = Aabb::from_points(Point3::zero(), Point3::new(2.0, 1.0, 1.0));
+    // two voxels along x with centres at x = 0.5 and 1.5
+    let mut raw = Vec::new();
+    [1.0f32, 3.0].iter().for_each(|v| raw.extend(v.to_le_bytes()));
+    let grid = VoxelGrid::from_raw(&raw, [2, 1, 1], bounds.clone()).unwrap();
+    assert_eq!(grid.max_density(), 3.0);
+    let at = |x: f64| grid.density(Point3::new(x, 0.5, 0.5));
+    assert_eq!(at(0.5), 1.0);
+    assert_eq!(at(1.0), 2.0);
+    assert_eq!(at(1.9), 3.0);
+    assert_eq!(at(0.1), 1.0);
+    assert_eq!(at(2.5), 0.0);
+    assert_eq!(grid.density(Point3::new(1.0, 0.5, 0.5) + Vec3::new(0.0, 0.7, 0.0)), 0.0);
+    assert!(VoxelGrid::from_raw(&raw, [3, 1, 1], bounds).is_err());
+}
@@ -0,0 +1,70 @@
+//! In-memory render target
+
+use anyhow::Result;
+use std::io::Write;
+use crate::color::Color;
+use crate::output::write_p3;
+use crate::tonemap::DisplayPipeline;
+
+/// Rendered image holding the linear (not gamma corrected) color of each pixel and the
+/// number of samples averaged into it. Pixels are stored row by row starting top left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Color>,
+    samples: Vec<u32>,
+}
+
+impl Framebuffer {
+    /// Black image of given dimensions with no samples taken
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Color::new(0.0, 0.0, 0.0); width * height],
+            samples: vec![0; width * height],
+        }
+    }
+    fn index(&self, i: usize, j: usize) -> usize {
+        assert!(i < self.width && j < self.height, "pixel ({i}, {j}) outside framebuffer");
+        j * self.width + i
+    }
+    /// Linear color of pixel in column i, row j
+    pub fn pixel(&self, i: usize, j: usize) -> Color {
+        self.pixels[self.index(i, j)]
+    }
+    /// Number of samples averaged into pixel in column i, row j
+    pub fn samples(&self, i: usize, j: usize) -> u32 {
+        self.samples[self.index(i, j)]
+    }
+    /// Store the average color of `samples` samples for pixel in column i, row j
+    pub fn set_pixel(&mut self, i: usize, j: usize, color: Color, samples: u32) {
+        let idx = self.index(i, j);
+        self.pixels[idx] = color;
+        self.samples[idx] = samples;
+    }
+    /// All pixels row by row
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+    /// Mutable access to all pixels for post-processing
+    pub fn pixels_mut(&mut self) -> &mut [Color] {
+        &mut self.pixels
+    }
+    /// Write image as ASCII PPM (P3) using the default display pipeline
+    pub fn write_ppm(&self, out: impl Write) -> Result<()> {
+        write_p3(self, out, &DisplayPipeline::default())
+    }
+}
+
+#[test]
+fn test_write_ppm() {
+    let mut fb = Framebuffer::new(2, 1);
+    fb.set_pixel(1, 0, Color::new(1.0, 0.25, 0.0), 4);
+    let mut out = Vec::new();
+    fb.write_ppm(&mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "P3\n2 1\n255\n0 0 0\n255 128 0\n");
+    assert_eq!(fb.samples(1, 0), 4);
+    assert_eq!(fb.samples(0, 0), 0);
+}
@@ -5,6 +5,9 @@ use crate::ray::Ray;
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::aabb::Aabb;
+use crate::color::Color;
+use crate::rng::Pcg32;
+use std::sync::Arc;
 
 // Add mods with types of hittable objects
 pub mod hittable_list;
@@ -12,6 +15,14 @@ pub mod sphere;
 pub mod bvh;
 pub mod quad;
 pub mod cube;
+pub mod triangle;
+pub mod mesh;
+pub mod instance;
+pub mod constant_medium;
+pub mod heterogeneous_medium;
+
+/// Stable identifier of an object in a world, its index in the list the world was built from
+pub type ObjectId = usize;
 
 /// Parameters of the hit. Material is borrowed from object hit. The material must live at least as long as the
 /// HitRecord does -> 'a
@@ -23,6 +34,8 @@ pub struct HitRecord<'a> {
     pub u: f64,
     pub v: f64,
     pub material: Box<&'a dyn Material>, // borrowed from object hit
+    pub vertex_color: Option<Color>, // interpolated from mesh vertices that have colors
+    pub object_id: Option<ObjectId>, // set by worlds that identify their objects, e.g. `SceneQuery`
 }
 impl<'a> HitRecord<'a> {
     pub fn new(
@@ -34,7 +47,7 @@ impl<'a> HitRecord<'a> {
         v: f64,
         material: Box<&'a dyn Material>
     ) -> Self {
-        HitRecord { p, normal, t, front_face, u, v, material }
+        HitRecord { p, normal, t, front_face, u, v, material, vertex_color: None, object_id: None }
     }
     /// Build a hit record from a ray. Automatically detects whether the hit is front-facing and
     /// adjusts the normal accordingly
@@ -57,10 +70,64 @@ impl<'a> HitRecord<'a> {
 }
 
 /// Trait indicating object that can be hit by a ray.
-pub trait Hittable {
-    /// Check if a hit occurs.
+pub trait Hittable: Send + Sync {
+    /// Closest hit on a solid surface. Participating media have no surface and are passed through.
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>>;
 
     /// Construct bounding box.
     fn bounding_box(&self) -> &Aabb;
+
+    /// Closest hit including scattering events inside participating media, whose distance is
+    /// drawn from rng. Solid objects only have their surface.
+    fn sample_hit(&self, r: &Ray, ray_t: Interval, _rng: &mut Pcg32) -> Option<HitRecord<'_>> {
+        self.hit(r, ray_t)
+    }
+
+    /// Whether any solid surface blocks the ray within ray_t. Unlike `hit` this can stop at the
+    /// first surface found rather than the closest, and builds no hit record.
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.hit(r, ray_t).is_some()
+    }
+
+    /// Fraction of light carried along the ray through participating media over ray_t.
+    /// Solid objects don't attenuate, they are found by `hit` instead.
+    fn transmittance(&self, _r: &Ray, _ray_t: Interval, _rng: &mut Pcg32) -> f64 {
+        1.0
+    }
+
+    /// Solid angle probability density of `random_direction` generating direction from origin.
+    /// Objects that can't be sampled as lights return 0.
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Random direction from origin toward a point on the object's surface
+    fn random_direction(&self, _origin: Point3, _rng: &mut Pcg32) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+}
+
+/// Shared objects, e.g. a mesh placed many times by instances that each hold an Arc to it
+impl<H: Hittable + ?Sized> Hittable for Arc<H> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        (**self).hit(r, ray_t)
+    }
+    fn bounding_box(&self) -> &Aabb {
+        (**self).bounding_box()
+    }
+    fn sample_hit(&self, r: &Ray, ray_t: Interval, rng: &mut Pcg32) -> Option<HitRecord<'_>> {
+        (**self).sample_hit(r, ray_t, rng)
+    }
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        (**self).occluded(r, ray_t)
+    }
+    fn transmittance(&self, r: &Ray, ray_t: Interval, rng: &mut Pcg32) -> f64 {
+        (**self).transmittance(r, ray_t, rng)
+    }
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        (**self).pdf_value(origin, direction)
+    }
+    fn random_direction(&self, origin: Point3, rng: &mut Pcg32) -> Vec3 {
+        (**self).random_direction(origin, rng)
+    }
 }
@@ -1,73 +1,333 @@
 //! Bounding volume heirarchy definition
 //!
-//! Bounding volume is hittable so included in hittable trait
+//! Bounding volume is hittable so included in hittable trait. Trees are built top down with
+//! the surface area heuristic (SAH) evaluated over a fixed number of bins per axis, which is
+//! deterministic so the same scene always gives the same tree.
 
 use crate::hittable::{ Hittable, HitRecord };
 use crate::aabb::Aabb;
 use crate::ray::Ray;
 use crate::interval::Interval;
-use crate::random_int;
-use std::cmp::Reverse;
-use std::rc::Rc;
+use crate::vec3::Vec3;
+use crate::rng::Pcg32;
+use std::sync::Arc;
 
-pub struct BvhNode {
-    left_node: Rc<dyn Hittable>,
-    right_node: Rc<dyn Hittable>,
+/// Relative cost of testing a ray against a node's box, compared to a primitive
+const TRAVERSAL_COST: f64 = 0.125;
+/// Relative cost of intersecting one primitive
+const INTERSECTION_COST: f64 = 1.0;
+/// Buckets of centroid positions candidate splits are evaluated between
+const BIN_COUNT: usize = 12;
+/// Nodes with more primitives than this are always split
+const MAX_LEAF_SIZE: usize = 4;
+
+/// Deepest tree built, bounding the traversal stack. Nodes this deep become leaves.
+const MAX_DEPTH: usize = 64;
+
+/// Linear BVH. Nodes are stored depth first in one array with each interior node followed by
+/// its first child, and leaves refer to runs of objects by index, so traversal is a loop over
+/// an explicit stack rather than recursive calls through `dyn Hittable`.
+pub struct Bvh {
+    nodes: Vec<LinearNode>,
+    objects: Vec<Arc<dyn Hittable>>, // ordered so each leaf's objects are contiguous
     bounding_box: Aabb,
+    cost: f64, // expected SAH cost of a ray that hits bounding_box
 }
 
-impl BvhNode {
-    pub fn new(
-        left_node: Rc<dyn Hittable>,
-        right_node: Rc<dyn Hittable>,
-        bounding_box: Aabb
-    ) -> Self {
-        BvhNode { left_node, right_node, bounding_box }
-    }
-    pub fn from_list(hlist: &mut [Rc<dyn Hittable>]) -> Self {
-        let comparator = random_int(0, 2);
-        let object_span = hlist.len();
-        let left_node;
-        let right_node;
-        if object_span == 1 {
-            left_node = hlist.get(0).unwrap().clone();
-            right_node = hlist.get(0).unwrap().clone();
-        } else if object_span == 2 {
-            left_node = hlist.get(0).unwrap().clone();
-            right_node = hlist.get(1).unwrap().clone();
-        } else {
-            hlist.sort_by_key(|x| Reverse(*x.bounding_box().axis(comparator)));
-            let mid = object_span / 2;
-            left_node = Rc::new(BvhNode::from_list(&mut hlist[0..mid]));
-            right_node = Rc::new(BvhNode::from_list(&mut hlist[mid..object_span]));
+struct LinearNode {
+    bounding_box: Aabb,
+    offset: u32, // leaves: index of first object, interiors: index of second child
+    count: u32, // objects in a leaf, 0 for interiors
+    axis: u8, // split axis of interiors, deciding which child is nearer to a ray
+}
+
+impl Bvh {
+    /// Build a tree over the objects, reordering the slice
+    pub fn from_list(hlist: &mut [Arc<dyn Hittable>]) -> Self {
+        let bounding_box = enclosing_box(hlist.iter().map(|object| object.bounding_box()));
+        let mut nodes = Vec::with_capacity(2 * hlist.len() / MAX_LEAF_SIZE + 1);
+        let cost = match hlist.is_empty() {
+            true => 0.0,
+            false => build(&mut nodes, hlist, 0, 0),
+        };
+        Bvh { nodes, objects: hlist.to_vec(), bounding_box, cost }
+    }
+    /// Expected cost of tracing a ray through the tree, in units of primitive intersections,
+    /// given it hits the root box. Lower is better.
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+
+    /// Calls visit with the objects of each leaf whose box the ray hits within ray_t, visiting
+    /// the nearer child of each node first. visit returns the new end of the interval, so a
+    /// closest hit query can skip boxes behind the best hit so far.
+    fn traverse<'a>(
+        &'a self,
+        r: &Ray,
+        ray_t: Interval,
+        mut visit: impl FnMut(&'a [Arc<dyn Hittable>], Interval) -> f64
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let inv_direction = Vec3::new(1.0 / r.direction.x, 1.0 / r.direction.y, 1.0 / r.direction.z);
+        let mut t_max = ray_t.max;
+        let mut stack = [0usize; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            if node.bounding_box.hit_inverse(r.origin, inv_direction, Interval::new(ray_t.min, t_max)) {
+                if node.count > 0 {
+                    let start = node.offset as usize;
+                    let objects = &self.objects[start..start + (node.count as usize)];
+                    t_max = visit(objects, Interval::new(ray_t.min, t_max));
+                } else {
+                    let (near, far) = match r.direction.axis(node.axis as i32) < 0.0 {
+                        true => (node.offset as usize, current + 1),
+                        false => (current + 1, node.offset as usize),
+                    };
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    current = near;
+                    continue;
+                }
+            }
+            if stack_len == 0 {
+                return;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
         }
-        let bounding_box = Aabb::from_boxes(left_node.bounding_box(), right_node.bounding_box());
-        BvhNode::new(left_node, right_node, bounding_box)
+    }
+
+    /// Closest of the hits found by query on each object in leaves the ray reaches
+    fn closest_hit<'a>(
+        &'a self,
+        r: &Ray,
+        ray_t: Interval,
+        mut query: impl FnMut(&'a Arc<dyn Hittable>, Interval) -> Option<HitRecord<'a>>
+    ) -> Option<HitRecord<'a>> {
+        let mut current_best = None;
+        self.traverse(r, ray_t, |objects, itv| {
+            let mut closest_so_far = itv.max;
+            for object in objects {
+                if let Some(h) = query(object, Interval::new(itv.min, closest_so_far)) {
+                    closest_so_far = h.t;
+                    current_best = Some(h);
+                }
+            }
+            closest_so_far
+        });
+        current_best
     }
 }
 
-impl Hittable for BvhNode {
-    /// Check if a hit occurs.
-    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
-        // check if bounding box is hit
-        match self.bounding_box.hit(r, ray_t) {
-            // if box hit check both children for hits
-            Some(_hit_itv) => {
-                let hit_left = self.left_node.hit(r, ray_t);
-                let right_max_itv = match &hit_left {
-                    None => ray_t.max,
-                    Some(rec) => rec.t,
-                };
-                let hit_right = self.right_node.hit(r, Interval::new(ray_t.min, right_max_itv));
-                hit_left.or(hit_right)
+/// Append the subtree over hlist, whose objects start at offset in the final object list, and
+/// return its cost
+fn build(nodes: &mut Vec<LinearNode>, hlist: &mut [Arc<dyn Hittable>], offset: usize, depth: usize) -> f64 {
+    let bounding_box = enclosing_box(hlist.iter().map(|object| object.bounding_box()));
+    let leaf_cost = INTERSECTION_COST * (hlist.len() as f64);
+    let split = match hlist.len() > 1 && depth + 1 < MAX_DEPTH {
+        true => find_split(hlist, &bounding_box),
+        false => None,
+    };
+    let must_split = hlist.len() > MAX_LEAF_SIZE && depth + 1 < MAX_DEPTH;
+    let (mid, axis) = match split {
+        Some((mid, axis, split_cost)) if split_cost < leaf_cost || must_split => (mid, axis),
+        // centroids all coincide so bins can't separate them, split in half instead
+        None if must_split => (hlist.len() / 2, 0),
+        _ => {
+            nodes.push(LinearNode { bounding_box, offset: offset as u32, count: hlist.len() as u32, axis: 0 });
+            return leaf_cost;
+        }
+    };
+
+    let index = nodes.len();
+    nodes.push(LinearNode { bounding_box, offset: 0, count: 0, axis: axis as u8 });
+    let (left_list, right_list) = hlist.split_at_mut(mid);
+    let left_cost = build(nodes, left_list, offset, depth + 1);
+    let second = nodes.len();
+    let right_cost = build(nodes, right_list, offset + mid, depth + 1);
+    nodes[index].offset = second as u32;
+
+    let area = nodes[index].bounding_box.surface_area();
+    TRAVERSAL_COST +
+        (nodes[index + 1].bounding_box.surface_area() * left_cost +
+            nodes[second].bounding_box.surface_area() * right_cost) / area
+}
+
+/// Best binned SAH split of the objects. Partitions hlist so the first mid objects go left and
+/// returns mid with the split axis and expected cost of the split.
+fn find_split(hlist: &mut [Arc<dyn Hittable>], bounding_box: &Aabb) -> Option<(usize, i32, f64)> {
+    let centroid_bounds = enclosing_centroids(hlist);
+    let area = bounding_box.surface_area();
+    let bin_of = |object: &Arc<dyn Hittable>, axis: i32| -> usize {
+        let extent = centroid_bounds[axis as usize];
+        let offset = (object.bounding_box().centroid()[axis as usize] - extent.min) / extent.size();
+        ((offset * (BIN_COUNT as f64)) as usize).min(BIN_COUNT - 1)
+    };
+
+    let mut best: Option<(i32, usize, f64)> = None; // axis, first bin on the right, cost
+    for axis in 0..3 {
+        if centroid_bounds[axis as usize].size() <= 0.0 {
+            continue;
+        }
+        let mut counts = [0usize; BIN_COUNT];
+        let mut boxes: [Option<Aabb>; BIN_COUNT] = Default::default();
+        for object in hlist.iter() {
+            let bin = bin_of(object, axis);
+            counts[bin] += 1;
+            boxes[bin] = Some(match &boxes[bin] {
+                Some(b) => Aabb::from_boxes(b, object.bounding_box()),
+                None => object.bounding_box().clone(),
+            });
+        }
+        // area and count to the right of each boundary, then sweep from the left
+        let mut right_area = [0.0; BIN_COUNT];
+        let mut right_count = [0usize; BIN_COUNT];
+        let mut right_box: Option<Aabb> = None;
+        let mut count = 0;
+        for bin in (1..BIN_COUNT).rev() {
+            right_box = merge(right_box, &boxes[bin]);
+            count += counts[bin];
+            right_area[bin] = right_box.as_ref().map_or(0.0, Aabb::surface_area);
+            right_count[bin] = count;
+        }
+        let mut left_box: Option<Aabb> = None;
+        let mut left_count = 0;
+        for bin in 1..BIN_COUNT {
+            left_box = merge(left_box, &boxes[bin - 1]);
+            left_count += counts[bin - 1];
+            if left_count == 0 || right_count[bin] == 0 {
+                continue;
+            }
+            let left_area = left_box.as_ref().map_or(0.0, Aabb::surface_area);
+            let cost = TRAVERSAL_COST +
+                INTERSECTION_COST *
+                    (left_area * (left_count as f64) + right_area[bin] * (right_count[bin] as f64)) /
+                    area;
+            if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                best = Some((axis, bin, cost));
             }
-            // return None if box not hit
-            None => None,
         }
     }
 
+    let (axis, split_bin, cost) = best?;
+    // stable sort keeps the partition independent of anything but the input order
+    hlist.sort_by_key(|object| bin_of(object, axis) >= split_bin);
+    let mid = hlist.iter().take_while(|object| bin_of(object, axis) < split_bin).count();
+    Some((mid, axis, cost))
+}
+
+fn merge(acc: Option<Aabb>, other: &Option<Aabb>) -> Option<Aabb> {
+    match (acc, other) {
+        (Some(a), Some(b)) => Some(Aabb::from_boxes(&a, b)),
+        (a, b) => a.or(b.clone()),
+    }
+}
+
+/// Smallest box containing all the boxes
+fn enclosing_box<'a>(mut boxes: impl Iterator<Item = &'a Aabb>) -> Aabb {
+    match boxes.next() {
+        Some(first) => boxes.fold(first.clone(), |acc, b| Aabb::from_boxes(&acc, b)),
+        None => Aabb::new_zero(),
+    }
+}
+
+/// Smallest box containing the centroids of all objects, without the padding added to boxes
+fn enclosing_centroids(hlist: &[Arc<dyn Hittable>]) -> [Interval; 3] {
+    let mut itvs = [Interval::new(f64::INFINITY, f64::NEG_INFINITY); 3];
+    for object in hlist {
+        let c = object.bounding_box().centroid();
+        for (axis, itv) in itvs.iter_mut().enumerate() {
+            *itv = Interval::new(itv.min.min(c[axis]), itv.max.max(c[axis]));
+        }
+    }
+    itvs
+}
+
+impl Hittable for Bvh {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        self.closest_hit(r, ray_t, |object, itv| object.hit(r, itv))
+    }
+    fn sample_hit(&self, r: &Ray, ray_t: Interval, rng: &mut Pcg32) -> Option<HitRecord<'_>> {
+        self.closest_hit(r, ray_t, |object, itv| object.sample_hit(r, itv, rng))
+    }
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        let mut occluded = false;
+        self.traverse(r, ray_t, |objects, itv| {
+            occluded = objects.iter().any(|object| object.occluded(r, itv));
+            // an empty interval ends the traversal at the first blocker
+            match occluded {
+                true => f64::NEG_INFINITY,
+                false => itv.max,
+            }
+        });
+        occluded
+    }
+    fn transmittance(&self, r: &Ray, ray_t: Interval, rng: &mut Pcg32) -> f64 {
+        let mut transmittance = 1.0;
+        self.traverse(r, ray_t, |objects, itv| {
+            transmittance *= objects.iter().map(|object| object.transmittance(r, itv, rng)).product::<f64>();
+            // nothing gets through, an empty interval ends the traversal
+            match transmittance > 0.0 {
+                true => itv.max,
+                false => f64::NEG_INFINITY,
+            }
+        });
+        transmittance
+    }
+
     /// Construct bounding box.
     fn bounding_box(&self) -> &Aabb {
         &self.bounding_box
     }
 }
+
+#[test]
+fn test_sah_matches_list() {
+    use crate::color::Color;
+    use crate::hittable::hittable_list::HittableList;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::lambertian::Lambertian;
+    use crate::texture::SolidColor;
+    use crate::vec3::Point3;
+    let white = Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)));
+    let mut world = HittableList::new();
+    for i in 0..10 {
+        for j in 0..10 {
+            let (x, z) = (i as f64, j as f64);
+            world.add(Sphere::new_static(Point3::new(x * 1.5, (x * z).sin(), z * 1.5), 0.4 + 0.05 * (x + z).cos(), white));
+        }
+    }
+    let bvh = Bvh::from_list(&mut world.objects.clone());
+    // same input gives the same tree
+    assert_eq!(bvh.cost(), Bvh::from_list(&mut world.objects.clone()).cost());
+    // a good tree tests far fewer than all 100 spheres
+    assert!(bvh.cost() < 10.0, "{}", bvh.cost());
+
+    let mut rng = Pcg32::new(0, 0);
+    for _ in 0..2000 {
+        let origin = Point3::new(7.0, 0.0, 7.0) + 12.0 * Vec3::random_unit_vector(&mut rng);
+        let r = Ray::new(origin, Vec3::random_unit_vector(&mut rng), 0.0);
+        let expected = world.hit(&r, Interval::new(0.001, f64::INFINITY)).map(|rec| rec.t);
+        let actual = bvh.hit(&r, Interval::new(0.001, f64::INFINITY)).map(|rec| rec.t);
+        assert_eq!(actual, expected);
+        // occlusion over a segment agrees with the closest hit
+        let segment = Interval::new(0.001, 10.0);
+        let blocked = world.hit(&r, segment).is_some();
+        assert_eq!(world.occluded(&r, segment), blocked);
+        assert_eq!(bvh.occluded(&r, segment), blocked);
+    }
+
+    // identical objects can't be separated by SAH bins, they are still split into small leaves
+    let mut stack = HittableList::new();
+    for _ in 0..50 {
+        stack.add(Sphere::new_static(Point3::zero(), 1.0, white));
+    }
+    let bvh = Bvh::from_list(&mut stack.objects);
+    assert!(bvh.nodes.iter().all(|node| node.count as usize <= MAX_LEAF_SIZE));
+    let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+    assert_eq!(bvh.hit(&r, Interval::new(0.001, f64::INFINITY)).map(|rec| rec.t), Some(4.0));
+}
@@ -0,0 +1,105 @@
+//! Participating medium of constant density
+
+use crate::vec3::Vec3;
+use crate::hittable::{ HitRecord, Hittable };
+use crate::ray::Ray;
+use crate::interval::Interval;
+use crate::material::isotropic::Isotropic;
+use crate::texture::Texture;
+use crate::aabb::Aabb;
+use crate::rng::Pcg32;
+
+/// Fog or smoke filling a boundary object of type H, scattering light isotropically with
+/// albedo from texture T.
+///
+/// Rays travelling through the medium scatter after an exponentially distributed distance,
+/// so denser media scatter sooner. The boundary must be closed and convex: a ray is assumed
+/// to enter it once and leave it once.
+///
+/// Scattering events are drawn by `sample_hit`. The medium has no surface, so `hit` never
+/// reports it and shadow rays are dimmed through `transmittance` instead.
+pub struct ConstantMedium<H: Hittable, T: Texture> {
+    boundary: H,
+    neg_inv_density: f64,
+    phase_function: Isotropic<T>,
+}
+
+impl<H: Hittable, T: Texture> ConstantMedium<H, T> {
+    /// Density is the chance of scattering per unit distance travelled
+    pub fn new(boundary: H, density: f64, texture: T) -> Self {
+        ConstantMedium { boundary, neg_inv_density: -1.0 / density, phase_function: Isotropic::new(texture) }
+    }
+}
+
+impl<H: Hittable, T: Texture> Hittable for ConstantMedium<H, T> {
+    /// Media have no surface to block shadow rays
+    fn hit(&self, _r: &Ray, _ray_t: Interval) -> Option<HitRecord<'_>> {
+        None
+    }
+    fn sample_hit(&self, r: &Ray, ray_t: Interval, rng: &mut Pcg32) -> Option<HitRecord<'_>> {
+        let (t_enter, t_exit) = boundary_span(&self.boundary, r, ray_t)?;
+        let ray_length = r.direction.length();
+        let distance_inside_boundary = (t_exit - t_enter) * ray_length;
+        let hit_distance = self.neg_inv_density * (1.0 - rng.next_f64()).ln();
+        if hit_distance > distance_inside_boundary {
+            return None;
+        }
+
+        let t = t_enter + hit_distance / ray_length;
+        // normal and face are arbitrary, the phase function ignores them
+        Some(HitRecord::new(r.at(t), Vec3::new(1.0, 0.0, 0.0), t, true, 0.0, 0.0, Box::new(&self.phase_function)))
+    }
+    fn transmittance(&self, r: &Ray, ray_t: Interval, _rng: &mut Pcg32) -> f64 {
+        match boundary_span(&self.boundary, r, ray_t) {
+            Some((t_enter, t_exit)) => ((t_exit - t_enter) * r.direction.length() / self.neg_inv_density).exp(),
+            None => 1.0,
+        }
+    }
+    fn bounding_box(&self) -> &Aabb {
+        self.boundary.bounding_box()
+    }
+}
+
+/// Part of ray_t spent inside a closed convex boundary, found from where the whole line
+/// enters and leaves it
+pub(crate) fn boundary_span(boundary: &impl Hittable, r: &Ray, ray_t: Interval) -> Option<(f64, f64)> {
+    let enter = boundary.hit(r, Interval::new(f64::NEG_INFINITY, f64::INFINITY))?;
+    let exit = boundary.hit(r, Interval::new(enter.t + 0.0001, f64::INFINITY))?;
+    let t_enter = enter.t.max(ray_t.min).max(0.0);
+    let t_exit = exit.t.min(ray_t.max);
+    (t_enter < t_exit).then_some((t_enter, t_exit))
+}
+
+#[test]
+fn test_transmittance() {
+    use crate::color::Color;
+    use crate::hittable::cube::Cube;
+    use crate::material::lambertian::Lambertian;
+    use crate::texture::SolidColor;
+    use crate::vec3::Point3;
+    let white = SolidColor::new(Color::new(1.0, 1.0, 1.0));
+    let boundary = Cube::from_points(Point3::zero(), Point3::new(2.0, 2.0, 2.0), Lambertian::new(white));
+    let medium = ConstantMedium::new(boundary, 0.5, white);
+
+    // chance of crossing 2 units unscattered is exp(-0.5 * 2)
+    let r = Ray::new(Point3::new(-1.0, 1.0, 1.0), Vec3::new(2.0, 0.0, 0.0), 0.0);
+    let n = 100_000;
+    let mut rng = Pcg32::new(0, 0);
+    let mut passed = 0;
+    for _ in 0..n {
+        match medium.sample_hit(&r, Interval::new(0.001, f64::INFINITY), &mut rng) {
+            None => passed += 1,
+            Some(rec) => assert!(rec.p.x >= 0.0 && rec.p.x <= 2.0),
+        }
+    }
+    let transmittance = (passed as f64) / (n as f64);
+    assert!((transmittance - (-1.0f64).exp()).abs() < 0.01, "{transmittance}");
+
+    // rays starting inside only travel the remaining distance
+    let inside = Ray::new(Point3::new(1.5, 1.0, 1.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+    let passed = (0..n)
+        .filter(|_| medium.sample_hit(&inside, Interval::new(0.001, f64::INFINITY), &mut rng).is_none())
+        .count();
+    let transmittance = (passed as f64) / (n as f64);
+    assert!((transmittance - (-0.25f64).exp()).abs() < 0.01, "{transmittance}");
+}
@@ -8,6 +8,7 @@ use crate::vec3::{ Point3, Vec3 };
 use crate::aabb::Aabb;
 use crate::ray::Ray;
 use crate::interval::Interval;
+use crate::rng::Pcg32;
 
 pub struct Cube<'a> {
     sides: HittableList<'a>,
@@ -16,7 +17,7 @@ impl<'a> Cube<'a> {
     fn new(sides: HittableList<'a>) -> Self {
         Cube { sides }
     }
-    pub fn from_points(a: Point3, b: Point3, material: impl Material + 'a + Copy) -> Self {
+    pub fn from_points(a: Point3, b: Point3, material: impl Material + 'a + Clone) -> Self {
         let mut sides = HittableList::new();
         let min_p = Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
         let max_p = Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
@@ -25,11 +26,11 @@ impl<'a> Cube<'a> {
         let dy = Vec3::new(0.0, max_p.y - min_p.y, 0.0);
         let dz = Vec3::new(0.0, 0.0, max_p.z - min_p.z);
 
-        sides.add(Quad::new_static(Point3::new(min_p.x, min_p.y, max_p.z), dx, dy, material));
-        sides.add(Quad::new_static(Point3::new(max_p.x, min_p.y, max_p.z), -dz, dy, material));
-        sides.add(Quad::new_static(Point3::new(max_p.x, min_p.y, min_p.z), -dx, dy, material));
-        sides.add(Quad::new_static(Point3::new(min_p.x, min_p.y, min_p.z), dz, dy, material));
-        sides.add(Quad::new_static(Point3::new(min_p.x, max_p.y, max_p.z), dx, -dz, material));
+        sides.add(Quad::new_static(Point3::new(min_p.x, min_p.y, max_p.z), dx, dy, material.clone()));
+        sides.add(Quad::new_static(Point3::new(max_p.x, min_p.y, max_p.z), -dz, dy, material.clone()));
+        sides.add(Quad::new_static(Point3::new(max_p.x, min_p.y, min_p.z), -dx, dy, material.clone()));
+        sides.add(Quad::new_static(Point3::new(min_p.x, min_p.y, min_p.z), dz, dy, material.clone()));
+        sides.add(Quad::new_static(Point3::new(min_p.x, max_p.y, max_p.z), dx, -dz, material.clone()));
         sides.add(Quad::new_static(Point3::new(min_p.x, min_p.y, min_p.z), dx, dz, material));
 
         Cube::new(sides)
@@ -43,4 +44,13 @@ impl<'a> Hittable for Cube<'a> {
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
         self.sides.hit(r, ray_t)
     }
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.sides.occluded(r, ray_t)
+    }
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.sides.pdf_value(origin, direction)
+    }
+    fn random_direction(&self, origin: Point3, rng: &mut Pcg32) -> Vec3 {
+        self.sides.random_direction(origin, rng)
+    }
 }
\ No newline at end of file
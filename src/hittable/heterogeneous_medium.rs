@@ -0,0 +1,165 @@
+//! Participating medium with density varying in space
+
+use crate::vec3::Vec3;
+use crate::color::Color;
+use crate::hittable::{ HitRecord, Hittable };
+use crate::hittable::constant_medium::boundary_span;
+use crate::ray::Ray;
+use crate::interval::Interval;
+use crate::material::{ Material, ScatterRecord };
+use crate::material::henyey_greenstein::HenyeyGreenstein;
+use crate::texture::SolidColor;
+use crate::density::DensityField;
+use crate::aabb::Aabb;
+use crate::rng::Pcg32;
+
+/// Medium filling a closed convex boundary of type H with density from field D.
+///
+/// Absorption and scattering coefficients are per unit density, so at point p light is
+/// absorbed at rate absorption * density(p) per unit distance. Absorbing particles also
+/// emit, an optically thick medium glows with radiance `emission`.
+///
+/// Collisions are found with delta tracking against the field's maximum density and shadow
+/// rays are attenuated with ratio tracking, both unbiased for any density field. Only
+/// `sample_hit` finds collisions, `hit` looks for surfaces and passes through the medium.
+pub struct HeterogeneousMedium<H: Hittable, D: DensityField> {
+    boundary: H,
+    density: D,
+    majorant: f64, // extinction at the maximum density
+    material: MediumMaterial,
+}
+
+impl<H: Hittable, D: DensityField> HeterogeneousMedium<H, D> {
+    /// Anisotropy in (-1, 1) is the mean cosine of the Henyey-Greenstein phase function
+    pub fn new(
+        boundary: H,
+        density: D,
+        absorption: f64,
+        scattering: f64,
+        emission: Color,
+        anisotropy: f64
+    ) -> Self {
+        let extinction = absorption + scattering;
+        let majorant = extinction * density.max_density();
+        // every collision both scatters and emits, weighted by their share of extinction
+        let (albedo, emitted) = match extinction > 0.0 {
+            true => (scattering / extinction, (absorption / extinction) * emission),
+            false => (0.0, Color::new(0.0, 0.0, 0.0)),
+        };
+        let phase = HenyeyGreenstein::new(SolidColor::new(Color::new(albedo, albedo, albedo)), anisotropy);
+        HeterogeneousMedium { boundary, density, majorant, material: MediumMaterial { phase, emitted } }
+    }
+
+    /// Chance a collision proposed against the majorant at t is real
+    fn collision_probability(&self, r: &Ray, t: f64) -> f64 {
+        self.density.density(r.at(t)) / self.density.max_density()
+    }
+
+    /// Next tentative collision after t, exponentially distributed with the majorant
+    fn next_collision(&self, t: f64, ray_length: f64, rng: &mut Pcg32) -> f64 {
+        t - (1.0 - rng.next_f64()).ln() / (self.majorant * ray_length)
+    }
+}
+
+impl<H: Hittable, D: DensityField> Hittable for HeterogeneousMedium<H, D> {
+    /// Media have no surface to block shadow rays
+    fn hit(&self, _r: &Ray, _ray_t: Interval) -> Option<HitRecord<'_>> {
+        None
+    }
+    /// Delta tracking, stepping through collisions with a homogeneous medium at the majorant
+    /// and accepting each with the ratio of the real density to the maximum
+    fn sample_hit(&self, r: &Ray, ray_t: Interval, rng: &mut Pcg32) -> Option<HitRecord<'_>> {
+        if self.majorant <= 0.0 {
+            return None;
+        }
+        let (t_enter, t_exit) = boundary_span(&self.boundary, r, ray_t)?;
+        let ray_length = r.direction.length();
+        let mut t = t_enter;
+        loop {
+            t = self.next_collision(t, ray_length, rng);
+            if t >= t_exit {
+                return None;
+            }
+            if rng.next_f64() < self.collision_probability(r, t) {
+                // normal and face are arbitrary, the phase function ignores them
+                return Some(
+                    HitRecord::new(r.at(t), Vec3::new(1.0, 0.0, 0.0), t, true, 0.0, 0.0, Box::new(&self.material))
+                );
+            }
+        }
+    }
+    /// Ratio tracking, multiplying in the chance of each tentative collision being null
+    fn transmittance(&self, r: &Ray, ray_t: Interval, rng: &mut Pcg32) -> f64 {
+        if self.majorant <= 0.0 {
+            return 1.0;
+        }
+        let Some((t_enter, t_exit)) = boundary_span(&self.boundary, r, ray_t) else {
+            return 1.0;
+        };
+        let ray_length = r.direction.length();
+        let mut transmittance = 1.0;
+        let mut t = t_enter;
+        loop {
+            t = self.next_collision(t, ray_length, rng);
+            if t >= t_exit || transmittance <= 0.0 {
+                return transmittance;
+            }
+            transmittance *= 1.0 - self.collision_probability(r, t);
+        }
+    }
+    fn bounding_box(&self) -> &Aabb {
+        self.boundary.bounding_box()
+    }
+}
+
+/// Scattering and emission at a real collision
+struct MediumMaterial {
+    phase: HenyeyGreenstein<SolidColor>,
+    emitted: Color,
+}
+impl Material for MediumMaterial {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut Pcg32) -> Option<ScatterRecord> {
+        self.phase.scatter(r_in, rec, rng)
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.phase.scattering_pdf(r_in, rec, scattered)
+    }
+    fn emit(&self, _r_in: &Ray, _rec: &HitRecord) -> Color {
+        self.emitted
+    }
+}
+
+#[test]
+fn test_delta_and_ratio_tracking() {
+    use crate::hittable::cube::Cube;
+    use crate::material::lambertian::Lambertian;
+    use crate::density::ProceduralDensity;
+    use crate::vec3::Point3;
+    let white = Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)));
+    let boundary = Cube::from_points(Point3::zero(), Point3::new(2.0, 2.0, 2.0), white);
+    // density rising linearly along x, optical depth across the cube is 0.5 * (2 * 2 / 2) = 1
+    let density = ProceduralDensity::new(|p: Point3| p.x, 2.0);
+    let medium = HeterogeneousMedium::new(boundary, density, 0.2, 0.3, Color::new(2.0, 2.0, 2.0), 0.5);
+
+    let r = Ray::new(Point3::new(-1.0, 1.0, 1.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+    let n = 100_000;
+    let mut rng = Pcg32::new(0, 0);
+    let mut passed = 0;
+    let mut ratio_sum = 0.0;
+    for _ in 0..n {
+        match medium.sample_hit(&r, Interval::new(0.001, f64::INFINITY), &mut rng) {
+            None => passed += 1,
+            Some(rec) => {
+                assert!(rec.p.x >= 0.0 && rec.p.x <= 2.0);
+                assert_eq!(rec.material.emit(&r, &rec), Color::new(0.8, 0.8, 0.8));
+            }
+        }
+        ratio_sum += medium.transmittance(&r, Interval::new(0.001, f64::INFINITY), &mut rng);
+    }
+    let expected = (-1.0f64).exp();
+    let delta = (passed as f64) / (n as f64);
+    let ratio = ratio_sum / (n as f64);
+    assert!((delta - expected).abs() < 0.01, "{delta}");
+    assert!((ratio - expected).abs() < 0.01, "{ratio}");
+    assert!(medium.hit(&r, Interval::new(0.001, f64::INFINITY)).is_none());
+}
@@ -4,15 +4,17 @@ use crate::hittable::{ Hittable, HitRecord };
 use crate::ray::Ray;
 use crate::interval::Interval;
 use crate::aabb::Aabb;
+use crate::vec3::{ Point3, Vec3 };
+use crate::rng::Pcg32;
 
-use std::rc::Rc;
+use std::sync::Arc;
 
 /// Implement a list of hittable items.
 ///
-/// Each object has the hittable trait and is stored in a Box. Each item
-/// must live at least as long as the hittable list -> 'a
+/// Each object has the hittable trait and is stored in an Arc. Each item must
+/// live at least as long as the hittable list -> 'a
 pub struct HittableList<'a> {
-    pub objects: Vec<Rc<dyn Hittable + 'a>>,
+    pub objects: Vec<Arc<dyn Hittable + 'a>>,
     bounding_box: Aabb,
 }
 
@@ -22,7 +24,12 @@ impl<'a> HittableList<'a> {
     }
     pub fn add(&mut self, object: impl Hittable + 'a) {
         self.bounding_box = Aabb::from_boxes(&self.bounding_box, object.bounding_box());
-        self.objects.push(Rc::new(object));
+        self.objects.push(Arc::new(object));
+    }
+}
+impl Default for HittableList<'_> {
+    fn default() -> Self {
+        HittableList::new()
     }
 }
 impl Hittable for HittableList<'_> {
@@ -34,17 +41,55 @@ impl Hittable for HittableList<'_> {
         let mut current_best = None;
         for object in &self.objects {
             // object lives for 'b
-            match object.hit(r, Interval::new(ray_t.min, closest_so_far)) {
-                Some(h) => {
-                    closest_so_far = h.t;
-                    current_best = Some(h);
-                }
-                None => (),
+            if let Some(h) = object.hit(r, Interval::new(ray_t.min, closest_so_far)) {
+                closest_so_far = h.t;
+                current_best = Some(h);
+            }
+        }
+        current_best
+    }
+    fn sample_hit(&self, r: &Ray, ray_t: Interval, rng: &mut Pcg32) -> Option<HitRecord<'_>> {
+        let mut closest_so_far = ray_t.max;
+        let mut current_best = None;
+        for object in &self.objects {
+            if let Some(h) = object.sample_hit(r, Interval::new(ray_t.min, closest_so_far), rng) {
+                closest_so_far = h.t;
+                current_best = Some(h);
             }
         }
         current_best
     }
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.objects.iter().any(|object| object.occluded(r, ray_t))
+    }
+    fn transmittance(&self, r: &Ray, ray_t: Interval, rng: &mut Pcg32) -> f64 {
+        let mut transmittance = 1.0;
+        for object in &self.objects {
+            transmittance *= object.transmittance(r, ray_t, rng);
+            if transmittance <= 0.0 {
+                break;
+            }
+        }
+        transmittance
+    }
     fn bounding_box(&self) -> &Aabb {
         &self.bounding_box
     }
+    /// Average density of all objects, matching `random_direction` picking one uniformly
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self.objects
+            .iter()
+            .map(|object| object.pdf_value(origin, direction))
+            .sum();
+        sum / (self.objects.len() as f64)
+    }
+    fn random_direction(&self, origin: Point3, rng: &mut Pcg32) -> Vec3 {
+        match self.objects.len() {
+            0 => Vec3::new(1.0, 0.0, 0.0),
+            n => self.objects[rng.int(0, (n as i32) - 1) as usize].random_direction(origin, rng),
+        }
+    }
 }
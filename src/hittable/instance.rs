@@ -0,0 +1,205 @@
+//! Affine transformed instances of hittable objects
+//!
+//! Instances holding an `Arc` to the same object share its geometry and acceleration
+//! structure, so a mesh placed many times is stored once. A `Bvh` over the instances then
+//! gives a two level structure: the top level finds instances, each instance's object finds
+//! the primitives.
+
+use crate::vec3::{ Mat3, Mat4, Point3, Vec3 };
+use crate::hittable::{ HitRecord, Hittable };
+use crate::ray::Ray;
+use crate::interval::Interval;
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::rng::Pcg32;
+use std::sync::Arc;
+
+/// Object of type H placed in the world by an affine transform.
+///
+/// Rays are moved into object space by the inverse transform, so t is the same in both
+/// spaces, and hit points and normals are moved back to world space.
+pub struct Instance<H: Hittable> {
+    object: H,
+    transform: Mat4, // object to world
+    inverse: Mat4, // world to object
+    normal_matrix: Mat3, // inverse transpose, taking object normals to world
+    bounding_box: Aabb,
+    material: Option<Arc<dyn Material>>, // replaces the object's materials when set
+}
+
+impl<H: Hittable> Instance<H> {
+    /// Panics if the transform can't be inverted, e.g. a scale by 0
+    pub fn new(object: H, transform: Mat4) -> Self {
+        let inverse = transform.inverse().expect("instance transform must be invertible");
+        // enclose the transformed corners of the object's box
+        let object_box = object.bounding_box();
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = -min;
+        for corner in 0..8 {
+            let pick = |axis: i32| {
+                let itv = object_box.axis(axis);
+                if corner & (1 << axis) == 0 { itv.min } else { itv.max }
+            };
+            let p = transform.transform_point(Point3::new(pick(0), pick(1), pick(2)));
+            min = Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+        let normal_matrix = inverse.linear().transpose();
+        Instance { object, transform, inverse, normal_matrix, bounding_box: Aabb::from_points(min, max), material: None }
+    }
+    pub fn translate(object: H, offset: Vec3) -> Self {
+        Instance::new(object, Mat4::translation(offset))
+    }
+    /// Rotate by angle degrees about an axis through the origin
+    pub fn rotate(object: H, axis: &Vec3, degrees: f64) -> Self {
+        Instance::new(object, Mat4::rotation(axis, degrees))
+    }
+    /// Scale by a separate factor along each axis about the origin
+    pub fn scale(object: H, factors: Vec3) -> Self {
+        Instance::new(object, Mat4::scale(factors))
+    }
+    /// Use material for every hit on this instance instead of the object's own
+    pub fn with_material(mut self, material: Arc<dyn Material>) -> Self {
+        self.material = Some(material);
+        self
+    }
+    fn to_object(&self, r: &Ray) -> Ray {
+        Ray::new(self.inverse.transform_point(r.origin), self.inverse.transform_vector(r.direction), r.time)
+    }
+    fn to_world<'a>(&'a self, mut rec: HitRecord<'a>) -> HitRecord<'a> {
+        rec.p = self.transform.transform_point(rec.p);
+        rec.normal = (self.normal_matrix * rec.normal).unit_vector();
+        if let Some(material) = &self.material {
+            rec.material = Box::new(material.as_ref());
+        }
+        rec
+    }
+}
+
+impl<H: Hittable> Hittable for Instance<H> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        self.object.hit(&self.to_object(r), ray_t).map(|rec| self.to_world(rec))
+    }
+    fn sample_hit(&self, r: &Ray, ray_t: Interval, rng: &mut Pcg32) -> Option<HitRecord<'_>> {
+        self.object.sample_hit(&self.to_object(r), ray_t, rng).map(|rec| self.to_world(rec))
+    }
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.object.occluded(&self.to_object(r), ray_t)
+    }
+    fn transmittance(&self, r: &Ray, ray_t: Interval, rng: &mut Pcg32) -> f64 {
+        self.object.transmittance(&self.to_object(r), ray_t, rng)
+    }
+    fn bounding_box(&self) -> &Aabb {
+        &self.bounding_box
+    }
+    /// Object space density scaled by the change in solid angle of the linear part of the
+    /// inverse transform, |det A| / |A d|^3 for unit world direction d
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let direction = direction.unit_vector();
+        let object_direction = self.inverse.transform_vector(direction);
+        let object_pdf = self.object.pdf_value(self.inverse.transform_point(origin), object_direction);
+        object_pdf * self.inverse.linear().determinant().abs() / object_direction.length().powi(3)
+    }
+    fn random_direction(&self, origin: Point3, rng: &mut Pcg32) -> Vec3 {
+        let object_direction = self.object.random_direction(self.inverse.transform_point(origin), rng);
+        self.transform.transform_vector(object_direction)
+    }
+}
+
+#[test]
+fn test_transformed_hit() {
+    use crate::color::Color;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::lambertian::Lambertian;
+    use crate::texture::SolidColor;
+    let white = Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)));
+    // unit sphere stretched into an ellipsoid with radii 2, 1, 1 then moved to x = 10
+    let sphere = Sphere::new_static(Point3::zero(), 1.0, white);
+    let transform = Mat4::translation(Vec3::new(10.0, 0.0, 0.0)) * Mat4::scale(Vec3::new(2.0, 1.0, 1.0));
+    let instance = Instance::new(sphere, transform);
+
+    let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+    let rec = instance.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+    assert!((rec.t - 8.0).abs() < 1e-9);
+    assert!((rec.p - Point3::new(8.0, 0.0, 0.0)).near_zero());
+    assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).near_zero());
+
+    // normal at 45 degrees around the ellipse is not the stretched object space normal
+    let p = Point3::new(10.0 + 2.0 * 0.5f64.sqrt(), 0.5f64.sqrt(), 0.0);
+    let r = Ray::new(p + Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+    let rec = instance.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+    assert!((rec.normal - Vec3::new(1.0, 2.0, 0.0).unit_vector()).length() < 1e-9);
+
+    let bbox = instance.bounding_box();
+    assert!((bbox.axis(0).min - 8.0).abs() < 1e-9 && (bbox.axis(0).max - 12.0).abs() < 1e-9);
+    assert!((bbox.axis(1).max - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_rotated_pdf_normalised() {
+    use crate::color::Color;
+    use crate::hittable::quad::Quad;
+    use crate::material::lambertian::Lambertian;
+    use crate::texture::SolidColor;
+    let white = Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)));
+    let quad = Quad::new_static(Point3::new(-1.0, 2.0, -1.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 2.0), white);
+    let transform = Mat4::rotation(&Vec3::new(1.0, 1.0, 0.0), 30.0) * Mat4::scale(Vec3::new(1.0, 0.5, 3.0));
+    let instance = Instance::new(quad, transform);
+    // integrating the density over all directions gives 1
+    let n = 200_000;
+    let mut rng = Pcg32::new(0, 0);
+    let sum: f64 = (0..n)
+        .map(|_| instance.pdf_value(Point3::zero(), Vec3::random_unit_vector(&mut rng)))
+        .sum();
+    let integral = sum * 4.0 * std::f64::consts::PI / (n as f64);
+    assert!((integral - 1.0).abs() < 0.05, "{integral}");
+    let direction = instance.random_direction(Point3::zero(), &mut rng);
+    assert!(instance.pdf_value(Point3::zero(), direction) > 0.0);
+}
+
+#[test]
+fn test_shared_mesh_instances() {
+    use crate::color::Color;
+    use crate::hittable::bvh::Bvh;
+    use crate::hittable::mesh::{ MeshVertices, TriangleMesh };
+    use crate::material::diffuse_light::DiffuseLight;
+    use crate::material::lambertian::Lambertian;
+    use crate::texture::SolidColor;
+    use crate::ray::Ray;
+    let white = Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)));
+    // unit square in the xy plane
+    let positions = vec![
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(1.0, 0.0, 0.0),
+        Point3::new(1.0, 1.0, 0.0),
+        Point3::new(0.0, 1.0, 0.0)
+    ];
+    let vertices = Arc::new(MeshVertices::new(positions, Vec::new(), Vec::new()));
+    let mesh: Arc<dyn Hittable> = Arc::new(TriangleMesh::new(vertices, vec![[0, 1, 2], [0, 2, 3]], white));
+
+    // a 10 x 10 grid of copies, every other one glowing
+    let glow: Arc<dyn Material> = Arc::new(DiffuseLight::new(SolidColor::new(Color::new(4.0, 4.0, 4.0))));
+    let mut instances: Vec<Arc<dyn Hittable>> = Vec::new();
+    for i in 0..100 {
+        let offset = Vec3::new(2.0 * ((i % 10) as f64), 2.0 * ((i / 10) as f64), 0.0);
+        let instance = Instance::translate(Arc::clone(&mesh), offset);
+        instances.push(match i % 2 {
+            0 => Arc::new(instance),
+            _ => Arc::new(instance.with_material(Arc::clone(&glow))),
+        });
+    }
+    let top_level = Bvh::from_list(&mut instances);
+    // the mesh itself is stored once
+    assert_eq!(Arc::strong_count(&mesh), 101);
+
+    let hit_at = |x: f64, y: f64| {
+        let r = Ray::new(Point3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        top_level.hit(&r, Interval::new(0.001, f64::INFINITY))
+    };
+    let rec = hit_at(4.5, 6.5).unwrap(); // instance 34
+    assert!((rec.t - 1.0).abs() < 1e-9);
+    assert_eq!(rec.material.emit(&Ray::new(rec.p, rec.normal, 0.0), &rec), Color::new(0.0, 0.0, 0.0));
+    let rec = hit_at(6.5, 6.5).unwrap(); // instance 35
+    assert_eq!(rec.material.emit(&Ray::new(rec.p, rec.normal, 0.0), &rec), Color::new(4.0, 4.0, 4.0));
+    assert!(hit_at(5.5, 6.5).is_none());
+}
@@ -0,0 +1,316 @@
+//! Indexed triangle mesh implementation
+
+use crate::vec3::{ Point3, Vec3 };
+use crate::hittable::{ HitRecord, Hittable };
+use crate::hittable::triangle::intersect;
+use crate::ray::Ray;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::aabb::Aabb;
+use crate::color::Color;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Most triangles stored in one leaf of the mesh bounding volume heirarchy
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+/// Size of the traversal stack. Median splits halve each node, so no mesh is this deep.
+const STACK_SIZE: usize = 64;
+
+/// Per-vertex attributes shared by the triangles of one or more meshes.
+/// Normals, uvs and colors are either empty or hold one entry per position.
+#[derive(Debug, Clone, Default)]
+pub struct MeshVertices {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+    pub colors: Vec<Color>,
+}
+
+impl MeshVertices {
+    pub fn new(positions: Vec<Point3>, normals: Vec<Vec3>, uvs: Vec<(f64, f64)>) -> Self {
+        MeshVertices { positions, normals, uvs, colors: Vec::new() }
+    }
+    /// Replace normals with the area weighted average of the normals of the faces around
+    /// each vertex
+    pub fn compute_normals(&mut self, triangles: &[[u32; 3]]) {
+        let mut normals = vec![Vec3::zero(); self.positions.len()];
+        for [a, b, c] in triangles {
+            let (a, b, c) = (*a as usize, *b as usize, *c as usize);
+            // cross product length is twice the face area
+            let n = (self.positions[b] - self.positions[a]).cross(&(self.positions[c] - self.positions[a]));
+            for i in [a, b, c] {
+                normals[i] += n;
+            }
+        }
+        self.normals = normals
+            .into_iter()
+            .map(|n| if n.near_zero() { n } else { n.unit_vector() })
+            .collect();
+    }
+}
+
+/// Triangles indexing into shared vertex arrays, all of material T.
+///
+/// The mesh builds its own bounding volume heirarchy over its triangles so it can be added to
+/// a world as a single object. When vertex normals are present they are interpolated across
+/// each triangle for smooth shading. Hit record u, v come from the vertex uvs when present,
+/// otherwise they are the barycentric weights of the triangle's second and third vertices.
+/// Vertex colors are interpolated into the hit record's `vertex_color`.
+pub struct TriangleMesh<T: Material> {
+    vertices: Arc<MeshVertices>,
+    triangles: Vec<[u32; 3]>,
+    material: T,
+    nodes: Vec<MeshNode>,
+    bounding_box: Aabb,
+}
+
+/// Node of the flattened mesh heirarchy. Interior nodes have their left child directly after
+/// them and `count` 0, leaves cover `count` triangles starting at `start`.
+struct MeshNode {
+    bounding_box: Aabb,
+    start: u32, // first triangle of leaf, or right child of interior node
+    count: u32,
+    axis: u8, // split axis of interior node, deciding which child is nearer to a ray
+}
+
+impl<T: Material> TriangleMesh<T> {
+    pub fn new(vertices: Arc<MeshVertices>, triangles: Vec<[u32; 3]>, material: T) -> Self {
+        let boxes: Vec<Aabb> = triangles
+            .iter()
+            .map(|[a, b, c]| {
+                let p = |i: &u32| vertices.positions[*i as usize];
+                Aabb::from_boxes(&Aabb::from_points(p(a), p(b)), &Aabb::from_points(p(a), p(c)))
+            })
+            .collect();
+        let mut order: Vec<u32> = (0..triangles.len() as u32).collect();
+        let mut nodes = Vec::new();
+        if !triangles.is_empty() {
+            build_nodes(&mut nodes, &mut order, 0, &boxes);
+        }
+        // store triangles in leaf order so leaves reference contiguous ranges
+        let triangles: Vec<[u32; 3]> = order.iter().map(|i| triangles[*i as usize]).collect();
+        let bounding_box = nodes.first().map_or(Aabb::new_zero(), |n| n.bounding_box.clone());
+        TriangleMesh { vertices, triangles, material, nodes, bounding_box }
+    }
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+    /// Calls visit with the triangle index range of each leaf whose box the ray hits within
+    /// ray_t, visiting the nearer child of each node first. visit returns the new end of the interval, so closest hit queries skip boxes
+    /// behind the best hit so far and any hit queries can stop by returning -infinity.
+    fn traverse(&self, r: &Ray, ray_t: Interval, mut visit: impl FnMut(Range<usize>, Interval) -> f64) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let inv_direction = Vec3::new(1.0 / r.direction.x, 1.0 / r.direction.y, 1.0 / r.direction.z);
+        let mut t_max = ray_t.max;
+        let mut stack = [0u32; STACK_SIZE];
+        let mut stack_len = 0;
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            if node.bounding_box.hit_inverse(r.origin, inv_direction, Interval::new(ray_t.min, t_max)) {
+                if node.count > 0 {
+                    let start = node.start as usize;
+                    t_max = visit(start..start + (node.count as usize), Interval::new(ray_t.min, t_max));
+                } else {
+                    let (near, far) = match r.direction.axis(node.axis as i32) < 0.0 {
+                        true => (node.start, (current + 1) as u32),
+                        false => ((current + 1) as u32, node.start),
+                    };
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    current = near as usize;
+                    continue;
+                }
+            }
+            if stack_len == 0 || t_max < ray_t.min {
+                return;
+            }
+            stack_len -= 1;
+            current = stack[stack_len] as usize;
+        }
+    }
+    /// Closest triangle hit as (t, u, v, triangle index)
+    fn closest_hit(&self, r: &Ray, ray_t: Interval) -> Option<(f64, f64, f64, usize)> {
+        let positions = &self.vertices.positions;
+        let mut current_best = None;
+        self.traverse(r, ray_t, |triangles, mut itv| {
+            for tri in triangles {
+                let [a, b, c] = self.triangles[tri].map(|i| positions[i as usize]);
+                if let Some((t, u, v)) = intersect(r, a, b - a, c - a, itv) {
+                    itv.max = t;
+                    current_best = Some((t, u, v, tri));
+                }
+            }
+            itv.max
+        });
+        current_best
+    }
+}
+
+/// Recursively build nodes over triangles order[..], whose first element is triangle
+/// `offset` of the final ordering. Splits at the median centroid of the longest axis.
+fn build_nodes(nodes: &mut Vec<MeshNode>, order: &mut [u32], offset: usize, boxes: &[Aabb]) {
+    let bounding_box = order
+        .iter()
+        .map(|i| &boxes[*i as usize])
+        .fold(boxes[order[0] as usize].clone(), |acc, b| Aabb::from_boxes(&acc, b));
+    let idx = nodes.len();
+    nodes.push(MeshNode { bounding_box, start: offset as u32, count: order.len() as u32, axis: 0 });
+    if order.len() <= MAX_LEAF_TRIANGLES {
+        return;
+    }
+    let centroid_box = order
+        .iter()
+        .map(|i| boxes[*i as usize].centroid())
+        .fold(Aabb::from_points(boxes[order[0] as usize].centroid(), boxes[order[0] as usize].centroid()), |acc, c| {
+            Aabb::from_boxes(&acc, &Aabb::from_points(c, c))
+        });
+    let axis = centroid_box.longest_axis();
+    let mid = order.len() / 2;
+    order.select_nth_unstable_by(mid, |a, b| {
+        let ca = boxes[*a as usize].centroid().axis(axis);
+        let cb = boxes[*b as usize].centroid().axis(axis);
+        ca.total_cmp(&cb)
+    });
+    let (left, right) = order.split_at_mut(mid);
+    build_nodes(nodes, left, offset, boxes);
+    let right_idx = nodes.len();
+    build_nodes(nodes, right, offset + mid, boxes);
+    nodes[idx].start = right_idx as u32;
+    nodes[idx].count = 0;
+    nodes[idx].axis = axis as u8;
+}
+
+impl<T: Material> Hittable for TriangleMesh<T> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let (t, b1, b2, tri) = self.closest_hit(r, ray_t)?;
+        let [ia, ib, ic] = self.triangles[tri].map(|i| i as usize);
+        let vertices = &self.vertices;
+        let b0 = 1.0 - b1 - b2;
+
+        let (pa, pb, pc) = (vertices.positions[ia], vertices.positions[ib], vertices.positions[ic]);
+        let geometric_normal = (pb - pa).cross(&(pc - pa)).unit_vector();
+        let front_face = r.direction.dot(&geometric_normal) < 0.0;
+
+        let mut normal = geometric_normal;
+        if !vertices.normals.is_empty() {
+            let shading_normal = b0 * vertices.normals[ia] + b1 * vertices.normals[ib] + b2 * vertices.normals[ic];
+            if !shading_normal.near_zero() {
+                normal = shading_normal.unit_vector();
+            }
+        }
+        // keep the normal on the side the ray arrived from
+        if normal.dot(&r.direction) > 0.0 {
+            normal = -normal;
+        }
+
+        let (u, v) = match vertices.uvs.is_empty() {
+            true => (b1, b2),
+            false => {
+                let (ua, va) = vertices.uvs[ia];
+                let (ub, vb) = vertices.uvs[ib];
+                let (uc, vc) = vertices.uvs[ic];
+                (b0 * ua + b1 * ub + b2 * uc, b0 * va + b1 * vb + b2 * vc)
+            }
+        };
+        let mut rec = HitRecord::new(r.at(t), normal, t, front_face, u, v, Box::new(&self.material));
+        if !vertices.colors.is_empty() {
+            rec.vertex_color = Some(b0 * vertices.colors[ia] + b1 * vertices.colors[ib] + b2 * vertices.colors[ic]);
+        }
+        Some(rec)
+    }
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        let positions = &self.vertices.positions;
+        let mut occluded = false;
+        self.traverse(r, ray_t, |triangles, itv| {
+            occluded = triangles.into_iter().any(|tri| {
+                let [a, b, c] = self.triangles[tri].map(|i| positions[i as usize]);
+                intersect(r, a, b - a, c - a, itv).is_some()
+            });
+            if occluded { f64::NEG_INFINITY } else { itv.max }
+        });
+        occluded
+    }
+    fn bounding_box(&self) -> &Aabb {
+        &self.bounding_box
+    }
+}
+
+#[test]
+fn test_mesh_matches_triangles() {
+    use crate::hittable::hittable_list::HittableList;
+    use crate::hittable::triangle::Triangle;
+    use crate::material::lambertian::Lambertian;
+    use crate::texture::SolidColor;
+    use crate::rng::Pcg32;
+    let white = Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)));
+
+    // bumpy grid of 2 * 16 * 16 triangles
+    let n = 17;
+    let mut rng = Pcg32::new(0, 0);
+    let positions: Vec<Point3> = (0..n * n)
+        .map(|i| Point3::new((i % n) as f64, rng.float(-0.5, 0.5), (i / n) as f64))
+        .collect();
+    let mut triangles = Vec::new();
+    for j in 0..n - 1 {
+        for i in 0..n - 1 {
+            let k = (j * n + i) as u32;
+            triangles.push([k, k + (n as u32), k + 1]);
+            triangles.push([k + 1, k + (n as u32), k + (n as u32) + 1]);
+        }
+    }
+    let mut list = HittableList::new();
+    for [a, b, c] in &triangles {
+        let p = |i: &u32| positions[*i as usize];
+        list.add(Triangle::new(p(a), p(b), p(c), white));
+    }
+    let mesh = TriangleMesh::new(Arc::new(MeshVertices::new(positions, vec![], vec![])), triangles, white);
+    assert_eq!(mesh.len(), 512);
+
+    for _ in 0..500 {
+        let origin = Point3::new(rng.float(-2.0, 18.0), 5.0, rng.float(-2.0, 18.0));
+        let r = Ray::new(origin, Vec3::new(rng.float(-1.0, 1.0), -1.0, rng.float(-1.0, 1.0)), 0.0);
+        let expected = list.hit(&r, Interval::new(0.001, f64::INFINITY));
+        let actual = mesh.hit(&r, Interval::new(0.001, f64::INFINITY));
+        assert_eq!(expected.is_some(), actual.is_some());
+        if let (Some(expected), Some(actual)) = (expected, actual) {
+            assert!((expected.t - actual.t).abs() < 1e-9);
+            assert!((expected.normal - actual.normal).near_zero());
+        }
+        assert_eq!(mesh.occluded(&r, Interval::new(0.001, f64::INFINITY)), list.occluded(&r, Interval::new(0.001, f64::INFINITY)));
+    }
+}
+
+#[test]
+fn test_smooth_normals() {
+    use crate::material::lambertian::Lambertian;
+    use crate::texture::SolidColor;
+    // two triangles folded along x = 0 like a roof
+    let positions = vec![
+        Point3::new(-1.0, 0.0, 0.0),
+        Point3::new(0.0, 1.0, 0.0),
+        Point3::new(0.0, 1.0, -1.0),
+        Point3::new(1.0, 0.0, 0.0),
+        Point3::new(-1.0, 0.0, -1.0),
+        Point3::new(1.0, 0.0, -1.0),
+    ];
+    let triangles = vec![[0, 1, 2], [0, 2, 4], [1, 3, 5], [1, 5, 2]];
+    let mut vertices = MeshVertices::new(positions, vec![], vec![]);
+    vertices.compute_normals(&triangles);
+    let mesh = TriangleMesh::new(
+        Arc::new(vertices),
+        triangles,
+        Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)))
+    );
+    // the ridge normal points straight up
+    let r = Ray::new(Point3::new(-0.001, 5.0, -0.5), Vec3::new(0.0, -1.0, 0.0), 0.0);
+    let rec = mesh.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+    assert!(rec.front_face);
+    assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 0.01);
+}
@@ -6,6 +6,7 @@ use crate::ray::Ray;
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::aabb::Aabb;
+use crate::rng::Pcg32;
 
 
 #[derive(Debug, Clone)]
@@ -16,11 +17,13 @@ pub struct Quad<T: Material> {
     w: Vec3, //vector related to planar coordinates of quad
     normal: Vec3,
     d: f64, //defines the plane of the quadrilateral
+    area: f64,
     material: T,
     bounding_box: Aabb,
 }
 
 impl<T: Material> Quad<T> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         q: Point3,
         u: Vec3,
@@ -31,7 +34,8 @@ impl<T: Material> Quad<T> {
         material: T,
         bounding_box: Aabb
     ) -> Self {
-        Quad { q, u, v, w, normal, d, material, bounding_box }
+        let area = u.cross(&v).length();
+        Quad { q, u, v, w, normal, d, area, material, bounding_box }
     }
     pub fn new_static(q: Point3, u: Vec3, v: Vec3, material: T) -> Self {
         //Compute bounding box
@@ -44,10 +48,8 @@ impl<T: Material> Quad<T> {
         let w = n / n.dot(&n);
         Quad::new(q, u, v, w, normal, d, material, bounding_box)
     }
-}
-
-impl<T: Material> Hittable for Quad<T> {
-    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+    /// Ray parameter t and planar coordinates of the hit, if the ray hits within ray_t
+    fn intersect(&self, r: &Ray, ray_t: Interval) -> Option<(f64, f64, f64)> {
         let denom = self.normal.dot(&r.direction);
 
         // check if ray is parallel
@@ -62,34 +64,42 @@ impl<T: Material> Hittable for Quad<T> {
         }
 
         // check if ray hits within planar coordinates
-        let intersection = r.at(t);
-        let planar_hitpt_vector = intersection - self.q;
+        let planar_hitpt_vector = r.at(t) - self.q;
         let alpha = self.w.dot(&planar_hitpt_vector.cross(&self.v));
         let beta = self.w.dot(&self.u.cross(&planar_hitpt_vector));
 
-        let planar_coords = get_planar_coords(alpha, beta);
-        match planar_coords {
-            None => {
-                return None;
-            }
-            Some((u, v)) => {
-                return Some(
-                    HitRecord::from_ray(
-                        r,
-                        intersection,
-                        self.normal,
-                        t,
-                        u,
-                        v,
-                        Box::new(&self.material)
-                    )
-                );
-            }
-        }
+        let (u, v) = get_planar_coords(alpha, beta)?;
+        Some((t, u, v))
+    }
+}
+
+impl<T: Material> Hittable for Quad<T> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let (t, u, v) = self.intersect(r, ray_t)?;
+        Some(HitRecord::from_ray(r, r.at(t), self.normal, t, u, v, Box::new(&self.material)))
+    }
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.intersect(r, ray_t).is_some()
     }
     fn bounding_box(&self) -> &Aabb {
         &self.bounding_box
     }
+    /// Convert uniform area density of the quad to solid angle density seen from origin
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        match self.hit(&Ray::new(origin, direction, 0.0), Interval::new(0.001, f64::INFINITY)) {
+            None => 0.0,
+            Some(rec) => {
+                let distance_squared = rec.t * rec.t * direction.length_squared();
+                let cosine = (direction.dot(&rec.normal) / direction.length()).abs();
+                distance_squared / (cosine * self.area)
+            }
+        }
+    }
+    /// Direction to a uniformly distributed point on the quad
+    fn random_direction(&self, origin: Point3, rng: &mut Pcg32) -> Vec3 {
+        let p = self.q + rng.next_f64() * self.u + rng.next_f64() * self.v;
+        p - origin
+    }
 }
 
 /// Returns hit coordinates u, v if hit occurs within plane, otherwise returns None
@@ -100,3 +110,26 @@ fn get_planar_coords(alpha: f64, beta: f64) -> Option<(f64, f64)> {
     }
     None
 }
+
+#[test]
+fn test_pdf_integrates_to_one() {
+    use crate::color::Color;
+    use crate::texture::SolidColor;
+    use crate::material::lambertian::Lambertian;
+    let quad = Quad::new_static(
+        Point3::new(-1.0, 2.0, -0.5),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+        Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)))
+    );
+    // integrate solid angle density over the sphere of directions
+    let n = 200_000;
+    let mut rng = Pcg32::new(0, 0);
+    let sum: f64 = (0..n)
+        .map(|_| quad.pdf_value(Point3::zero(), Vec3::random_unit_vector(&mut rng)))
+        .sum();
+    let integral = sum / (n as f64) * 4.0 * std::f64::consts::PI;
+    assert!((integral - 1.0).abs() < 0.05, "integral {integral}");
+    let direction = quad.random_direction(Point3::zero(), &mut rng);
+    assert!(quad.pdf_value(Point3::zero(), direction) > 0.0);
+}
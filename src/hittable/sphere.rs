@@ -1,11 +1,13 @@
 //! Sphere object implementation
 
-use crate::vec3::{ Point3, Vec3 };
+use crate::vec3::{ Onb, Point3, Vec3 };
 use crate::hittable::{ HitRecord, Hittable };
 use crate::ray::Ray;
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::aabb::Aabb;
+use crate::rng::Pcg32;
+use std::f64::consts::PI;
 
 #[derive(Debug, Clone)]
 /// Defines sphere of a specific material T
@@ -36,6 +38,27 @@ impl<T: Material> Sphere<T> {
         let bounding_box = Aabb::from_boxes(&bounding_box1, &bounding_box2);
         Sphere::new(r, radius, material, bounding_box)
     }
+    /// Smallest t in ray_t where the ray meets the sphere centred at center
+    fn nearest_root(&self, r: &Ray, center: Point3, ray_t: Interval) -> Option<f64> {
+        let oc = center - r.origin;
+        let a = r.direction.length_squared();
+        let h = r.direction.dot(&oc);
+        let c = oc.length_squared() - self.radius * self.radius;
+        let discriminant = h * h - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let sqrtd = discriminant.sqrt();
+
+        let mut root = (h - sqrtd) / a;
+        if !ray_t.surrounds(root) {
+            root = (h + sqrtd) / a;
+            if !ray_t.surrounds(root) {
+                return None;
+            }
+        }
+        Some(root)
+    }
     /// Find u, v coordinates on the unit sphere centered at the origin given a point on it, p
     fn get_uv(p: Point3) -> SphereCoords {
         let theta = (-p.y).acos();
@@ -60,24 +83,7 @@ impl<T: Material> Hittable for Sphere<T> {
     ///
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
         let current_center = self.center.at(r.time);
-        let oc = current_center - r.origin;
-        let a = r.direction.length_squared();
-        let h = r.direction.dot(&oc);
-        let c = oc.length_squared() - self.radius * self.radius;
-        let discriminant = h * h - a * c;
-        if discriminant < 0.0 {
-            return None;
-        }
-        let sqrtd = discriminant.sqrt();
-
-        let mut root = (h - sqrtd) / a;
-        if !ray_t.surrounds(root) {
-            root = (h + sqrtd) / a;
-            if !ray_t.surrounds(root) {
-                return None;
-            }
-        }
-        let t = root;
+        let t = self.nearest_root(r, current_center, ray_t)?;
         let p = r.at(t);
         let normal = (p - current_center) / self.radius;
         let sphere_coords = Sphere::<T>::get_uv(normal);
@@ -93,7 +99,71 @@ impl<T: Material> Hittable for Sphere<T> {
             )
         )
     }
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.nearest_root(r, self.center.at(r.time), ray_t).is_some()
+    }
     fn bounding_box(&self) -> &Aabb {
         &self.bounding_box
     }
+    /// Density of uniformly sampling the cone of directions the sphere subtends from origin
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        if self.hit(&Ray::new(origin, direction, 0.0), Interval::new(0.001, f64::INFINITY)).is_none() {
+            return 0.0;
+        }
+        let distance_squared = (self.center.at(0.0) - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            // every direction from inside hits the sphere
+            return 1.0 / (4.0 * PI);
+        }
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+    /// Direction uniformly distributed inside the cone the sphere subtends from origin
+    fn random_direction(&self, origin: Point3, rng: &mut Pcg32) -> Vec3 {
+        let direction = self.center.at(0.0) - origin;
+        let distance_squared = direction.length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return Vec3::random_unit_vector(rng);
+        }
+        let uvw = Onb::new(&direction);
+        uvw.transform(random_to_sphere(rng, radius_squared, distance_squared))
+    }
+}
+
+/// Random direction in the cone toward a sphere, in coordinates where the sphere lies along z
+fn random_to_sphere(rng: &mut Pcg32, radius_squared: f64, distance_squared: f64) -> Vec3 {
+    let r1 = rng.next_f64();
+    let r2 = rng.next_f64();
+    let z = 1.0 + r2 * ((1.0 - radius_squared / distance_squared).sqrt() - 1.0);
+
+    let phi = 2.0 * PI * r1;
+    let x = phi.cos() * (1.0 - z * z).sqrt();
+    let y = phi.sin() * (1.0 - z * z).sqrt();
+    Vec3::new(x, y, z)
+}
+
+#[test]
+fn test_pdf_integrates_to_one() {
+    use crate::color::Color;
+    use crate::texture::SolidColor;
+    use crate::material::lambertian::Lambertian;
+    let sphere = Sphere::new_static(
+        Point3::new(0.0, 3.0, 1.0),
+        1.5,
+        Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)))
+    );
+    let mut rng = Pcg32::new(0, 0);
+    for origin in [Point3::zero(), Point3::new(0.0, 3.5, 1.0)] {
+        let n = 200_000;
+        let sum: f64 = (0..n)
+            .map(|_| sphere.pdf_value(origin, Vec3::random_unit_vector(&mut rng)))
+            .sum();
+        let integral = sum / (n as f64) * 4.0 * PI;
+        assert!((integral - 1.0).abs() < 0.05, "integral {integral}");
+        let direction = sphere.random_direction(origin, &mut rng);
+        assert!(sphere.pdf_value(origin, direction) > 0.0);
+    }
 }
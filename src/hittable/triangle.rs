@@ -0,0 +1,123 @@
+//! Triangle object implementation
+
+use crate::vec3::{ Point3, Vec3 };
+use crate::hittable::{ HitRecord, Hittable };
+use crate::ray::Ray;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::aabb::Aabb;
+use crate::rng::Pcg32;
+
+/// Triangle with vertices a, b, c of a specific material T.
+/// The front face is the side the vertices appear counter-clockwise from.
+#[derive(Debug, Clone)]
+pub struct Triangle<T: Material> {
+    a: Point3,
+    ab: Vec3, // edge from a to b
+    ac: Vec3, // edge from a to c
+    normal: Vec3,
+    area: f64,
+    material: T,
+    bounding_box: Aabb,
+}
+
+impl<T: Material> Triangle<T> {
+    pub fn new(a: Point3, b: Point3, c: Point3, material: T) -> Self {
+        let ab = b - a;
+        let ac = c - a;
+        let n = ab.cross(&ac);
+        let bounding_box = Aabb::from_boxes(&Aabb::from_points(a, b), &Aabb::from_points(a, c));
+        Triangle { a, ab, ac, normal: n.unit_vector(), area: 0.5 * n.length(), material, bounding_box }
+    }
+}
+
+impl<T: Material> Hittable for Triangle<T> {
+    /// Hit record u, v are the barycentric weights of vertices b and c
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let (t, u, v) = intersect(r, self.a, self.ab, self.ac, ray_t)?;
+        Some(HitRecord::from_ray(r, r.at(t), self.normal, t, u, v, Box::new(&self.material)))
+    }
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        intersect(r, self.a, self.ab, self.ac, ray_t).is_some()
+    }
+    fn bounding_box(&self) -> &Aabb {
+        &self.bounding_box
+    }
+    /// Convert uniform area density of the triangle to solid angle density seen from origin
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        match self.hit(&Ray::new(origin, direction, 0.0), Interval::new(0.001, f64::INFINITY)) {
+            None => 0.0,
+            Some(rec) => {
+                let distance_squared = rec.t * rec.t * direction.length_squared();
+                let cosine = (direction.dot(&rec.normal) / direction.length()).abs();
+                distance_squared / (cosine * self.area)
+            }
+        }
+    }
+    /// Direction to a uniformly distributed point on the triangle
+    fn random_direction(&self, origin: Point3, rng: &mut Pcg32) -> Vec3 {
+        let mut u = rng.next_f64();
+        let mut v = rng.next_f64();
+        // fold the far half of the parallelogram back onto the triangle
+        if u + v > 1.0 {
+            u = 1.0 - u;
+            v = 1.0 - v;
+        }
+        self.a + u * self.ab + v * self.ac - origin
+    }
+}
+
+/// Möller–Trumbore ray triangle intersection for triangle with vertex a and edges ab, ac.
+///
+/// Returns the ray parameter t and barycentric weights u, v of the far ends of ab and ac
+/// when the hit lies within ray_t.
+pub(crate) fn intersect(r: &Ray, a: Point3, ab: Vec3, ac: Vec3, ray_t: Interval) -> Option<(f64, f64, f64)> {
+    let p = r.direction.cross(&ac);
+    let det = ab.dot(&p);
+
+    // check if ray is parallel
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let s = r.origin - a;
+    let u = s.dot(&p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&ab);
+    let v = r.direction.dot(&q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = ac.dot(&q) * inv_det;
+    if !ray_t.surrounds(t) {
+        return None;
+    }
+    Some((t, u, v))
+}
+
+#[test]
+fn test_hit_barycentric() {
+    use crate::color::Color;
+    use crate::material::lambertian::Lambertian;
+    use crate::texture::SolidColor;
+    let triangle = Triangle::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(1.0, 0.0, 0.0),
+        Point3::new(0.0, 1.0, 0.0),
+        Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)))
+    );
+    let r = Ray::new(Point3::new(0.25, 0.5, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+    let rec = triangle.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+    assert!((rec.t - 2.0).abs() < 1e-12);
+    assert!((rec.u - 0.25).abs() < 1e-12 && (rec.v - 0.5).abs() < 1e-12);
+    assert!(rec.front_face);
+    assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+
+    let miss = Ray::new(Point3::new(0.75, 0.5, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+    assert!(triangle.hit(&miss, Interval::new(0.001, f64::INFINITY)).is_none());
+    let behind = Ray::new(Point3::new(0.25, 0.5, -2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+    assert!(triangle.hit(&behind, Interval::new(0.001, f64::INFINITY)).is_none());
+}
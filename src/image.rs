@@ -1,7 +1,7 @@
 //! Module for loading images
 
 use image::ImageReader;
-use anyhow::Result;
+use anyhow::{ Result, ensure };
 
 use crate::interval::Interval;
 
@@ -16,18 +16,24 @@ impl Image {
     fn new(width: usize, height: usize, bytes: Vec<u8>) -> Self {
         Image { width, height, bytes }
     }
+    /// Image from 8 bit r, g, b bytes stored row by row starting top left
+    pub fn from_rgb8(width: usize, height: usize, bytes: Vec<u8>) -> Result<Self> {
+        ensure!(bytes.len() == width * height * 3, "{width}x{height} image needs {} bytes, got {}", width * height * 3, bytes.len());
+        Ok(Image::new(width, height, bytes))
+    }
     pub fn from_file(filename: &str) -> Result<Self> {
         let img = ImageReader::open(filename)?.decode()?;
         let width = img.width() as usize;
         let height = img.height() as usize;
-        let bytes = img.as_bytes().to_vec();
+        // pixel_data expects 8 bit rgb whatever the file stores
+        let bytes = img.to_rgb8().into_raw();
         Ok(Image::new(width, height, bytes))
     }
     pub fn pixel_data(&self, i: usize, j: usize) -> (u8, u8, u8) {
         let x = Interval::new(0.0, (self.width - 1) as f64).itv_clamp(i as f64) as usize;
         let y = Interval::new(0.0, (self.height - 1) as f64).itv_clamp(j as f64) as usize;
-        let bytes_per_pixel = 3 as usize;
-        let bytes_per_scanline = (self.width * bytes_per_pixel) as usize;
+        let bytes_per_pixel = 3;
+        let bytes_per_scanline = self.width * bytes_per_pixel;
         let idx = (y * bytes_per_scanline) + (x * bytes_per_pixel);
         (self.bytes[idx], self.bytes[idx+1], self.bytes[idx+2])
     }
@@ -1,6 +1,6 @@
 //! Interval object
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 /// Linear float interval from min to max
 pub struct Interval {
     pub min: f64,
@@ -8,7 +8,7 @@ pub struct Interval {
 }
 impl Interval {
     pub fn new(min: f64, max: f64) -> Self {
-        Interval { min: min, max: max }
+        Interval { min, max }
     }
     /// Create new interval where min is always < max
     pub fn new_bound_check(a: f64, b: f64) -> Self {
@@ -44,7 +44,7 @@ impl Interval {
         } else if x > self.max {
             return self.max;
         }
-        return x;
+        x
     }
     /// Adds padding delta/2 to each end of interval and returns new interval
     pub fn expand(&self, delta: f64) -> Self {
@@ -54,6 +54,11 @@ impl Interval {
 }
 
 impl std::cmp::Eq for Interval {}
+impl std::cmp::PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
 impl std::cmp::Ord for Interval {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         if self.size() < other.size() {
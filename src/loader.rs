@@ -0,0 +1,73 @@
+//! Load models from files made by external modelling tools
+//!
+//! Loaders return a `HittableList` of triangle meshes, one per group and material, that can
+//! be added to a world directly or passed to `Bvh::from_list`. Formats without materials
+//! also expose the raw `MeshData` so callers can pick their own.
+
+pub mod obj;
+pub mod gltf;
+pub mod ply;
+pub mod stl;
+
+use anyhow::{ Result, anyhow, bail };
+use std::path::Path;
+use std::sync::Arc;
+use crate::color::Color;
+use crate::hittable::hittable_list::HittableList;
+use crate::hittable::mesh::{ MeshVertices, TriangleMesh };
+use crate::material::Material;
+use crate::material::lambertian::Lambertian;
+use crate::texture::SolidColor;
+use crate::texture::vertex_color::VertexColorTexture;
+
+/// Color of surfaces that have no material or vertex colors
+const DEFAULT_ALBEDO: f64 = 0.8;
+
+/// Triangles and vertex attributes read from a file, before a material is chosen
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub vertices: MeshVertices,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+impl MeshData {
+    pub fn into_mesh<T: Material>(self, material: T) -> TriangleMesh<T> {
+        TriangleMesh::new(Arc::new(self.vertices), self.triangles, material)
+    }
+    /// Lambertian material colored by the vertex colors when there are any
+    pub fn default_material(&self) -> Arc<dyn Material> {
+        let grey = Color::new(DEFAULT_ALBEDO, DEFAULT_ALBEDO, DEFAULT_ALBEDO);
+        match self.vertices.colors.is_empty() {
+            true => default_material(),
+            false => Arc::new(Lambertian::new(VertexColorTexture::new(grey))),
+        }
+    }
+}
+
+/// Material of surfaces a file gives no material for
+pub(crate) fn default_material() -> Arc<dyn Material> {
+    let grey = Color::new(DEFAULT_ALBEDO, DEFAULT_ALBEDO, DEFAULT_ALBEDO);
+    Arc::new(Lambertian::new(SolidColor::new(grey)))
+}
+
+/// Load model from path, picking the format from the file extension
+pub fn load(path: impl AsRef<Path>) -> Result<HittableList<'static>> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .ok_or_else(|| anyhow!("no file extension on {}", path.display()))?;
+    let mesh_data = match extension.to_ascii_lowercase().as_str() {
+        "obj" => return obj::load_obj(path),
+        "gltf" | "glb" => return Ok(self::gltf::load_gltf(path)?.objects),
+        "ply" => ply::load_ply(path)?,
+        "stl" => stl::load_stl(path)?,
+        other => bail!("unsupported model file extension .{other}"),
+    };
+    let mut list = HittableList::new();
+    if !mesh_data.triangles.is_empty() {
+        let material = mesh_data.default_material();
+        list.add(mesh_data.into_mesh(material));
+    }
+    Ok(list)
+}
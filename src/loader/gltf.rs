@@ -0,0 +1,289 @@
+//! glTF 2.0 scene loader
+//!
+//! Loads `.gltf` files with embedded or external buffers and images, and binary `.glb` files.
+//! Node transforms are baked into mesh vertices, so a mesh used by several nodes is copied
+//! for each. Only triangle list primitives are loaded.
+//!
+//! Metallic-roughness materials are approximated:
+//! - emission (scaled by `KHR_materials_emissive_strength`) gives a `DiffuseLight`
+//! - `KHR_materials_transmission` above 0.5 gives a `Dialectric` with index from
+//!   `KHR_materials_ior`, or 1.5
+//! - metallic above 0.5 gives a `Metal` of the base color, with fuzz equal to the squared
+//!   roughness so its Phong lobe matches the usual roughness to exponent mapping
+//! - anything else is `Lambertian`, using the base color texture when present
+//!
+//! Perspective cameras become `CameraArgs` looking down the camera node's -z axis.
+
+use anyhow::{ Context, Result, anyhow };
+use ::gltf::camera::Projection;
+use ::gltf::image::Format;
+use ::gltf::mesh::Mode;
+use std::path::Path;
+use std::sync::Arc;
+use crate::vec3::{ Mat4, Point3, Vec3 };
+use crate::color::Color;
+use crate::camera::CameraArgs;
+use crate::hittable::hittable_list::HittableList;
+use crate::hittable::mesh::{ MeshVertices, TriangleMesh };
+use crate::material::Material;
+use crate::material::lambertian::Lambertian;
+use crate::material::metal::Metal;
+use crate::material::dialectric::Dialectric;
+use crate::material::diffuse_light::DiffuseLight;
+use crate::texture::{ Texture, SolidColor };
+use crate::texture::image::ImageTexture;
+use crate::image::Image;
+use crate::loader::default_material;
+
+/// Objects and cameras of a glTF scene
+pub struct GltfScene {
+    pub objects: HittableList<'static>,
+    pub cameras: Vec<CameraArgs>, // in node order, with default image settings
+}
+
+/// Load glTF or GLB file, resolving external buffers and images relative to it
+pub fn load_gltf(path: impl AsRef<Path>) -> Result<GltfScene> {
+    let path = path.as_ref();
+    let (document, buffers, images) = ::gltf::import(path)
+        .with_context(|| format!("failed to load {}", path.display()))?;
+    build_scene(&document, &buffers, &images).with_context(|| format!("failed to load {}", path.display()))
+}
+
+/// Parse GLB, or glTF with embedded data, from memory
+pub fn read_gltf(data: &[u8]) -> Result<GltfScene> {
+    let (document, buffers, images) = ::gltf::import_slice(data)?;
+    build_scene(&document, &buffers, &images)
+}
+
+fn build_scene(
+    document: &::gltf::Document,
+    buffers: &[::gltf::buffer::Data],
+    images: &[::gltf::image::Data]
+) -> Result<GltfScene> {
+    let textures = images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| to_texture(image).with_context(|| format!("image {i}")))
+        .collect::<Result<Vec<_>>>()?;
+    let materials: Vec<Arc<dyn Material>> = document
+        .materials()
+        .map(|material| to_material(&material, &textures))
+        .collect();
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| anyhow!("file has no scenes"))?;
+
+    let mut gltf_scene = GltfScene { objects: HittableList::new(), cameras: Vec::new() };
+    for node in scene.nodes() {
+        add_node(&node, &Mat4::identity(), buffers, &materials, &mut gltf_scene)?;
+    }
+    Ok(gltf_scene)
+}
+
+/// Add node and its children, with parent_transform taking the node's parent to world space
+fn add_node(
+    node: &::gltf::Node,
+    parent_transform: &Mat4,
+    buffers: &[::gltf::buffer::Data],
+    materials: &[Arc<dyn Material>],
+    scene: &mut GltfScene
+) -> Result<()> {
+    // glTF stores matrices column by column
+    let local = Mat4::new(node.transform().matrix().map(|column| column.map(|x| x as f64))).transpose();
+    let transform = *parent_transform * local;
+
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives().filter(|p| p.mode() == Mode::Triangles) {
+            let material = match primitive.material().index() {
+                Some(i) => materials[i].clone(),
+                None => default_material(),
+            };
+            let (vertices, triangles) = read_primitive(&primitive, buffers, &transform)
+                .with_context(|| format!("mesh {} primitive {}", mesh.index(), primitive.index()))?;
+            if !triangles.is_empty() {
+                scene.objects.add(TriangleMesh::new(Arc::new(vertices), triangles, material));
+            }
+        }
+    }
+    if let Some(camera) = node.camera()
+        && let Projection::Perspective(perspective) = camera.projection()
+    {
+        let lookfrom = transform.transform_point(Point3::zero());
+        let forward = transform.transform_vector(Vec3::new(0.0, 0.0, -1.0)).unit_vector();
+        let vup = transform.transform_vector(Vec3::new(0.0, 1.0, 0.0)).unit_vector();
+        scene.cameras.push(CameraArgs::new(
+            perspective.aspect_ratio().map_or(16.0 / 9.0, |a| a as f64),
+            400,
+            100,
+            50,
+            Color::new(0.0, 0.0, 0.0),
+            (perspective.yfov() as f64).to_degrees(),
+            lookfrom,
+            lookfrom + forward,
+            vup,
+            0.0,
+            1.0
+        ));
+    }
+    for child in node.children() {
+        add_node(&child, &transform, buffers, materials, scene)?;
+    }
+    Ok(())
+}
+
+/// Read primitive vertices in world space and its triangles
+fn read_primitive(
+    primitive: &::gltf::Primitive,
+    buffers: &[::gltf::buffer::Data],
+    transform: &Mat4
+) -> Result<(MeshVertices, Vec<[u32; 3]>)> {
+    // normals transform by the inverse transpose. Nodes scaled to nothing can't be hit.
+    let Some(normal_matrix) = transform.linear().inverse().map(|m| m.transpose()) else {
+        return Ok((MeshVertices::default(), Vec::new()));
+    };
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+    let positions: Vec<Point3> = reader
+        .read_positions()
+        .ok_or_else(|| anyhow!("primitive has no positions"))?
+        .map(|[x, y, z]| transform.transform_point(Point3::new(x as f64, y as f64, z as f64)))
+        .collect();
+    let normals: Vec<Vec3> = match reader.read_normals() {
+        Some(normals) => normals
+            .map(|[x, y, z]| (normal_matrix * Vec3::new(x as f64, y as f64, z as f64)).unit_vector())
+            .collect(),
+        None => Vec::new(),
+    };
+    // glTF puts v = 0 at the top of images, image textures at the bottom
+    let uvs: Vec<(f64, f64)> = match reader.read_tex_coords(0) {
+        Some(uvs) => uvs.into_f32().map(|[u, v]| (u as f64, 1.0 - (v as f64))).collect(),
+        None => Vec::new(),
+    };
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+    if let Some(index) = indices.iter().find(|i| (**i as usize) >= positions.len()) {
+        return Err(anyhow!("index {index} out of range, {} vertices", positions.len()));
+    }
+    // mirroring transforms reverse the winding, swap corners to keep front faces outside
+    let mirrored = transform.linear().determinant() < 0.0;
+    let triangles = indices
+        .chunks_exact(3)
+        .map(|t| match mirrored {
+            true => [t[0], t[2], t[1]],
+            false => [t[0], t[1], t[2]],
+        })
+        .collect();
+    Ok((MeshVertices::new(positions, normals, uvs), triangles))
+}
+
+fn to_material(material: &::gltf::Material, textures: &[Arc<dyn Texture>]) -> Arc<dyn Material> {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _alpha] = pbr.base_color_factor().map(|x| x as f64);
+    let base_color = Color::new(r, g, b);
+
+    let strength = material.emissive_strength().unwrap_or(1.0) as f64;
+    let [er, eg, eb] = material.emissive_factor().map(|x| (x as f64) * strength);
+    let emission = Color::new(er, eg, eb);
+    if emission.max_component() > 0.0 {
+        return Arc::new(DiffuseLight::new(SolidColor::new(emission)));
+    }
+    if material.transmission().is_some_and(|t| t.transmission_factor() > 0.5) {
+        return Arc::new(Dialectric::new(material.ior().unwrap_or(1.5) as f64));
+    }
+    if pbr.metallic_factor() > 0.5 {
+        let roughness = pbr.roughness_factor() as f64;
+        return Arc::new(Metal::new(base_color, roughness * roughness));
+    }
+    match pbr.base_color_texture() {
+        Some(info) => Arc::new(Lambertian::new(textures[info.texture().source().index()].clone())),
+        None => Arc::new(Lambertian::new(SolidColor::new(base_color))),
+    }
+}
+
+/// Convert decoded image to an 8 bit rgb texture
+fn to_texture(image: &::gltf::image::Data) -> Result<Arc<dyn Texture>> {
+    // channels and bytes per channel, 16 bit channels keep their high byte
+    let (channels, channel_size) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let byte = |pixel: &[u8], channel: usize| -> u8 {
+        let c = &pixel[channel * channel_size..(channel + 1) * channel_size];
+        match channel_size {
+            1 => c[0],
+            2 => u16::from_le_bytes([c[0], c[1]]).to_be_bytes()[0],
+            _ => (f32::from_le_bytes([c[0], c[1], c[2], c[3]]).clamp(0.0, 1.0) * 255.0) as u8,
+        }
+    };
+    let mut bytes = Vec::with_capacity((image.width * image.height * 3) as usize);
+    for pixel in image.pixels.chunks_exact(channels * channel_size) {
+        match channels {
+            1 | 2 => bytes.extend([byte(pixel, 0); 3]),
+            _ => bytes.extend([byte(pixel, 0), byte(pixel, 1), byte(pixel, 2)]),
+        }
+    }
+    let image = Image::from_rgb8(image.width as usize, image.height as usize, bytes)?;
+    Ok(Arc::new(ImageTexture::new(image)))
+}
+
+#[test]
+fn test_read_glb() {
+    use crate::hittable::Hittable;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+    // triangle mesh under a translated parent node, and a camera rotated to look down -x
+    let json = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 2] }],
+        "nodes": [
+            { "translation": [0, 0, -5], "children": [1] },
+            { "mesh": 0, "scale": [2, 2, 2] },
+            { "camera": 0, "rotation": [0, 0.7071068, 0, 0.7071068] }
+        ],
+        "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.5, "aspectRatio": 1.5, "znear": 0.1 } }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+        "materials": [{ "emissiveFactor": [1, 0.5, 0] }],
+        "buffers": [{ "byteLength": 36 }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "accessors": [{
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0, 0, 0], "max": [1, 1, 0]
+        }]
+    }"#;
+    let mut json = json.as_bytes().to_vec();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    let bin: Vec<u8> = [0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].iter().flat_map(|x| x.to_le_bytes()).collect();
+    let mut glb = b"glTF".to_vec();
+    glb.extend(2u32.to_le_bytes());
+    glb.extend(((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+    glb.extend((json.len() as u32).to_le_bytes());
+    glb.extend(b"JSON");
+    glb.extend(&json);
+    glb.extend((bin.len() as u32).to_le_bytes());
+    glb.extend(b"BIN\0");
+    glb.extend(&bin);
+
+    let scene = read_gltf(&glb).unwrap();
+    let r = Ray::new(Point3::new(1.0, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+    let rec = scene.objects.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+    assert!((rec.t - 5.0).abs() < 1e-6);
+    assert_eq!(rec.material.emit(&r, &rec), Color::new(1.0, 0.5, 0.0));
+    let miss = Ray::new(Point3::new(1.5, 1.5, 0.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+    assert!(scene.objects.hit(&miss, Interval::new(0.001, f64::INFINITY)).is_none());
+
+    let camera = &scene.cameras[0];
+    assert_eq!(camera.aspect_ratio, 1.5);
+    assert!((camera.vfov - 0.5f64.to_degrees()).abs() < 1e-9);
+    assert!((camera.lookat - camera.lookfrom - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-6);
+}
@@ -0,0 +1,408 @@
+//! Wavefront OBJ and MTL loader
+//!
+//! Supports positions, texture coordinates, normals, polygon faces (fan triangulated, so
+//! polygons should be convex), groups, objects and materials from `mtllib` files. Other
+//! statements such as smoothing groups and lines are ignored.
+//!
+//! MTL materials are mapped onto the closest material this renderer has:
+//! - `Ke` above zero gives a `DiffuseLight`
+//! - `d` below 1 or a transparent `illum` model gives a `Dialectric` with index `Ni`
+//! - `Ks` brighter than `Kd` gives a `Metal`, with fuzz chosen so its lobe matches
+//!   Phong exponent `Ns`
+//! - anything else is `Lambertian`, textured by `map_Kd` when present
+
+use anyhow::{ Context, Result, anyhow, bail };
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use crate::vec3::{ Point3, Vec3 };
+use crate::color::Color;
+use crate::hittable::hittable_list::HittableList;
+use crate::hittable::mesh::{ MeshVertices, TriangleMesh };
+use crate::material::Material;
+use crate::material::lambertian::Lambertian;
+use crate::material::metal::Metal;
+use crate::material::dialectric::Dialectric;
+use crate::material::diffuse_light::DiffuseLight;
+use crate::texture::{ Texture, SolidColor };
+use crate::texture::image::ImageTexture;
+use crate::image::Image;
+use crate::loader::default_material;
+
+/// Position, texture coordinate and normal indices of one face corner
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// Faces sharing a group and material, which become one mesh
+struct FaceGroup {
+    material: Arc<dyn Material>,
+    triangles: Vec<[Corner; 3]>,
+}
+
+/// Load OBJ file and the MTL files it references, resolved relative to the OBJ file
+pub fn load_obj(path: impl AsRef<Path>) -> Result<HittableList<'static>> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    parse_obj(&source, base_dir).with_context(|| format!("failed to load {}", path.display()))
+}
+
+/// Parse OBJ source, resolving `mtllib` paths relative to base_dir
+pub fn parse_obj(source: &str, base_dir: &Path) -> Result<HittableList<'static>> {
+    let mut positions: Vec<Point3> = Vec::new();
+    let mut texcoords: Vec<(f64, f64)> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    let mut groups: Vec<FaceGroup> = Vec::new();
+    let mut current = FaceGroup { material: default_material(), triangles: Vec::new() };
+
+    for (n, line) in source.lines().enumerate() {
+        let mut tokens = line.split('#').next().unwrap_or("").split_whitespace();
+        let Some(keyword) = tokens.next() else { continue };
+        let args: Vec<&str> = tokens.collect();
+        let mut parse_line = || -> Result<()> {
+            match keyword {
+                "v" => {
+                    let [x, y, z] = parse_floats(&args)?;
+                    positions.push(Point3::new(x, y, z));
+                }
+                "vt" => {
+                    let u = parse_floats::<1>(&args)?[0];
+                    let v = match args.get(1) {
+                        Some(v) => parse_float(v)?,
+                        None => 0.0,
+                    };
+                    texcoords.push((u, v));
+                }
+                "vn" => {
+                    let [x, y, z] = parse_floats(&args)?;
+                    normals.push(Vec3::new(x, y, z));
+                }
+                "f" => {
+                    if args.len() < 3 {
+                        bail!("face needs at least 3 vertices, found {}", args.len());
+                    }
+                    let corners = args
+                        .iter()
+                        .map(|token| {
+                            parse_corner(token, positions.len(), texcoords.len(), normals.len())
+                        })
+                        .collect::<Result<Vec<Corner>>>()?;
+                    for i in 1..corners.len() - 1 {
+                        current.triangles.push([corners[0], corners[i], corners[i + 1]]);
+                    }
+                }
+                "g" | "o" => {
+                    let material = current.material.clone();
+                    start_group(&mut groups, &mut current, material);
+                }
+                "usemtl" => {
+                    let name = args.join(" ");
+                    let material = materials
+                        .get(&name)
+                        .ok_or_else(|| anyhow!("unknown material {name}"))?
+                        .clone();
+                    start_group(&mut groups, &mut current, material);
+                }
+                "mtllib" => {
+                    for file in &args {
+                        let path = base_dir.join(file);
+                        materials.extend(load_mtl(&path)?);
+                    }
+                }
+                _ => (),
+            }
+            Ok(())
+        };
+        parse_line().with_context(|| format!("line {}: {}", n + 1, line.trim()))?;
+    }
+    groups.push(current);
+
+    let mut list = HittableList::new();
+    for group in groups.into_iter().filter(|g| !g.triangles.is_empty()) {
+        let (vertices, triangles) = build_vertices(&group.triangles, &positions, &texcoords, &normals);
+        list.add(TriangleMesh::new(Arc::new(vertices), triangles, group.material));
+    }
+    Ok(list)
+}
+
+/// Finish the current group, if it has faces, and begin a new one
+fn start_group(groups: &mut Vec<FaceGroup>, current: &mut FaceGroup, material: Arc<dyn Material>) {
+    let next = FaceGroup { material, triangles: Vec::new() };
+    let finished = std::mem::replace(current, next);
+    if !finished.triangles.is_empty() {
+        groups.push(finished);
+    }
+}
+
+/// Gather the vertices used by triangles into shared arrays, merging corners with the same
+/// indices. Normals and uvs are only kept when every corner has them.
+fn build_vertices(
+    triangles: &[[Corner; 3]],
+    positions: &[Point3],
+    texcoords: &[(f64, f64)],
+    normals: &[Vec3]
+) -> (MeshVertices, Vec<[u32; 3]>) {
+    let has_uvs = triangles.iter().flatten().all(|c| c.1.is_some());
+    let has_normals = triangles.iter().flatten().all(|c| c.2.is_some());
+    let mut vertices = MeshVertices::default();
+    let mut indices: HashMap<Corner, u32> = HashMap::new();
+    let mut mesh_triangles = Vec::with_capacity(triangles.len());
+    for triangle in triangles {
+        let triangle = triangle.map(|corner| {
+            *indices.entry(corner).or_insert_with(|| {
+                let (p, t, n) = corner;
+                vertices.positions.push(positions[p]);
+                if let (true, Some(t)) = (has_uvs, t) {
+                    vertices.uvs.push(texcoords[t]);
+                }
+                if let (true, Some(n)) = (has_normals, n) {
+                    vertices.normals.push(normals[n]);
+                }
+                (vertices.positions.len() - 1) as u32
+            })
+        });
+        mesh_triangles.push(triangle);
+    }
+    (vertices, mesh_triangles)
+}
+
+/// Parse face corner `v`, `v/vt`, `v//vn` or `v/vt/vn` into zero based indices
+fn parse_corner(token: &str, positions: usize, texcoords: usize, normals: usize) -> Result<Corner> {
+    let mut parts = token.split('/');
+    let position = parse_index(parts.next().unwrap_or(""), positions, "vertex")?;
+    let texcoord = match parts.next() {
+        None | Some("") => None,
+        Some(t) => Some(parse_index(t, texcoords, "texture coordinate")?),
+    };
+    let normal = match parts.next() {
+        None | Some("") => None,
+        Some(n) => Some(parse_index(n, normals, "normal")?),
+    };
+    if parts.next().is_some() {
+        bail!("malformed face vertex {token}");
+    }
+    Ok((position, texcoord, normal))
+}
+
+/// Convert one based OBJ index, or negative index counting back from the latest element,
+/// to a zero based index into `len` elements
+fn parse_index(token: &str, len: usize, kind: &str) -> Result<usize> {
+    let index: i64 = token.parse().map_err(|_| anyhow!("invalid {kind} index {token}"))?;
+    let resolved = match index {
+        i if i > 0 => i - 1,
+        i if i < 0 => (len as i64) + i,
+        _ => bail!("{kind} index 0 is invalid, indices start at 1"),
+    };
+    if resolved < 0 || resolved >= (len as i64) {
+        bail!("{kind} index {index} out of range, {len} defined so far");
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_float(token: &str) -> Result<f64> {
+    token.parse().map_err(|_| anyhow!("invalid number {token}"))
+}
+
+/// Parse the first N arguments as floats, ignoring any after them
+fn parse_floats<const N: usize>(args: &[&str]) -> Result<[f64; N]> {
+    if args.len() < N {
+        bail!("expected {N} numbers, found {}", args.len());
+    }
+    let mut values = [0.0; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = parse_float(arg)?;
+    }
+    Ok(values)
+}
+
+/// Properties of one `newmtl` entry
+struct MtlDescription {
+    diffuse: Color, // Kd
+    specular: Color, // Ks
+    emission: Color, // Ke
+    shininess: f64, // Ns, Phong exponent
+    refraction_index: f64, // Ni
+    dissolve: f64, // d, opacity
+    illum: i32,
+    diffuse_texture: Option<Arc<dyn Texture>>, // map_Kd
+}
+
+impl MtlDescription {
+    fn new() -> Self {
+        MtlDescription {
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            specular: Color::new(0.0, 0.0, 0.0),
+            emission: Color::new(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            refraction_index: 1.0,
+            dissolve: 1.0,
+            illum: 2,
+            diffuse_texture: None,
+        }
+    }
+    fn to_material(&self) -> Arc<dyn Material> {
+        if self.emission.max_component() > 0.0 {
+            return Arc::new(DiffuseLight::new(SolidColor::new(self.emission)));
+        }
+        if self.dissolve < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            return Arc::new(Dialectric::new(self.refraction_index));
+        }
+        if self.specular.max_component() > self.diffuse.max_component() {
+            // inverse of the exponent Metal uses for its lobe
+            let fuzz = (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt();
+            return Arc::new(Metal::new(self.specular, fuzz));
+        }
+        match &self.diffuse_texture {
+            Some(texture) => Arc::new(Lambertian::new(texture.clone())),
+            None => Arc::new(Lambertian::new(SolidColor::new(self.diffuse))),
+        }
+    }
+}
+
+/// Load MTL file, resolving texture paths relative to it
+pub fn load_mtl(path: impl AsRef<Path>) -> Result<HashMap<String, Arc<dyn Material>>> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    parse_mtl(&source, base_dir).with_context(|| format!("failed to load {}", path.display()))
+}
+
+/// Parse MTL source, resolving texture paths relative to base_dir
+pub fn parse_mtl(source: &str, base_dir: &Path) -> Result<HashMap<String, Arc<dyn Material>>> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlDescription)> = None;
+
+    for (n, line) in source.lines().enumerate() {
+        let mut tokens = line.split('#').next().unwrap_or("").split_whitespace();
+        let Some(keyword) = tokens.next() else { continue };
+        let args: Vec<&str> = tokens.collect();
+        let mut parse_line = || -> Result<()> {
+            if keyword == "newmtl" {
+                if let Some((name, description)) = current.take() {
+                    materials.insert(name, description.to_material());
+                }
+                if args.is_empty() {
+                    bail!("material needs a name");
+                }
+                current = Some((args.join(" "), MtlDescription::new()));
+                return Ok(());
+            }
+            let Some((_, description)) = current.as_mut() else {
+                bail!("{keyword} before newmtl");
+            };
+            match keyword {
+                "Kd" => description.diffuse = parse_color(&args)?,
+                "Ks" => description.specular = parse_color(&args)?,
+                "Ke" => description.emission = parse_color(&args)?,
+                "Ns" => description.shininess = parse_floats::<1>(&args)?[0],
+                "Ni" => description.refraction_index = parse_floats::<1>(&args)?[0],
+                "d" => description.dissolve = parse_floats::<1>(&args)?[0],
+                "Tr" => description.dissolve = 1.0 - parse_floats::<1>(&args)?[0],
+                "illum" => {
+                    let illum = args.first().ok_or_else(|| anyhow!("missing illumination model"))?;
+                    description.illum = illum.parse().map_err(|_| anyhow!("invalid illumination model {illum}"))?;
+                }
+                "map_Kd" => {
+                    // options such as -s come before the file name
+                    let file = args.last().ok_or_else(|| anyhow!("missing texture file"))?;
+                    let path = base_dir.join(file);
+                    let image = Image::from_file(&path.to_string_lossy())
+                        .with_context(|| format!("failed to load texture {}", path.display()))?;
+                    description.diffuse_texture = Some(Arc::new(ImageTexture::new(image)));
+                }
+                _ => (),
+            }
+            Ok(())
+        };
+        parse_line().with_context(|| format!("line {}: {}", n + 1, line.trim()))?;
+    }
+    if let Some((name, description)) = current {
+        materials.insert(name, description.to_material());
+    }
+    Ok(materials)
+}
+
+/// Parse `r g b`, or a single value used for all three
+fn parse_color(args: &[&str]) -> Result<Color> {
+    match args.len() {
+        1 | 2 => {
+            let x = parse_float(args[0])?;
+            Ok(Color::new(x, x, x))
+        }
+        _ => {
+            let [r, g, b] = parse_floats(args)?;
+            Ok(Color::new(r, g, b))
+        }
+    }
+}
+
+#[test]
+fn test_parse_obj() {
+    use crate::hittable::Hittable;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+    let source = "
+        # unit square in z = 0 as one quad, then a triangle at z = -1 using relative indices
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        vt 0 0
+        vt 1 0
+        vt 1 1
+        vt 0 1
+        vn 0 0 1
+        g square
+        f 1/1/1 2/2/1 3/3/1 4/4/1
+        g triangle
+        v 0 0 -1
+        v 1 0 -1
+        v 0 1 -1
+        f -3 -2 -1
+    ";
+    let list = parse_obj(source, Path::new("")).unwrap();
+    assert_eq!(list.objects.len(), 2);
+
+    let r = Ray::new(Point3::new(0.75, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+    let rec = list.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+    assert!((rec.t - 1.0).abs() < 1e-12);
+    assert!((rec.u - 0.75).abs() < 1e-12 && (rec.v - 0.25).abs() < 1e-12);
+
+    let r = Ray::new(Point3::new(0.25, 0.25, -2.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+    let rec = list.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+    assert!((rec.t - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_obj_errors() {
+    let message = |source| format!("{:#}", parse_obj(source, Path::new("")).err().unwrap());
+    let err = message("v 0 0 0\nv 1 0 0\nf 1 2 3\n");
+    assert!(err.starts_with("line 3: f 1 2 3"), "{err}");
+    assert!(err.contains("vertex index 3 out of range"), "{err}");
+    let err = message("v 0 0\n");
+    assert!(err.starts_with("line 1"), "{err}");
+    let err = message("usemtl missing\n");
+    assert!(err.contains("unknown material missing"), "{err}");
+}
+
+#[test]
+fn test_load_obj_with_mtl() {
+    use crate::hittable::Hittable;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+    let dir = std::env::temp_dir().join(format!("raytracing_obj_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("lamp.mtl"), "newmtl glow\nKe 4 4 4\n\nnewmtl matte\nKd 0.5 0.5 0.5\n").unwrap();
+    std::fs::write(
+        dir.join("lamp.obj"),
+        "mtllib lamp.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 -1\nusemtl glow\nf 1 2 3\nusemtl matte\nf 4 1 3\n"
+    ).unwrap();
+    let list = crate::loader::load(dir.join("lamp.obj")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let r = Ray::new(Point3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+    let rec = list.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+    assert_eq!(rec.material.emit(&r, &rec), Color::new(4.0, 4.0, 4.0));
+}
@@ -0,0 +1,428 @@
+//! Stanford PLY loader
+//!
+//! Reads ASCII and binary (either byte order) files. Vertex positions, normals (`nx ny nz`),
+//! texture coordinates (`u v`, `s t` or `texture_u texture_v`) and colors (`red green blue`)
+//! are kept, polygon faces are fan triangulated and any other elements are skipped.
+
+use anyhow::{ Context, Result, anyhow, bail };
+use std::path::Path;
+use crate::vec3::{ Point3, Vec3 };
+use crate::color::Color;
+use crate::loader::MeshData;
+
+/// Storage types of PLY properties
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    Int8,
+    Uint8,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self> {
+        match name {
+            "char" | "int8" => Ok(ScalarType::Int8),
+            "uchar" | "uint8" => Ok(ScalarType::Uint8),
+            "short" | "int16" => Ok(ScalarType::Int16),
+            "ushort" | "uint16" => Ok(ScalarType::Uint16),
+            "int" | "int32" => Ok(ScalarType::Int32),
+            "uint" | "uint32" => Ok(ScalarType::Uint32),
+            "float" | "float32" => Ok(ScalarType::Float32),
+            "double" | "float64" => Ok(ScalarType::Float64),
+            other => bail!("unknown property type {other}"),
+        }
+    }
+    fn size(&self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::Uint8 => 1,
+            ScalarType::Int16 | ScalarType::Uint16 => 2,
+            ScalarType::Int32 | ScalarType::Uint32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+    /// Scale taking stored color components to [0, 1]
+    fn color_scale(&self) -> f64 {
+        match self {
+            ScalarType::Uint8 => 1.0 / 255.0,
+            ScalarType::Uint16 => 1.0 / 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Scalar(String, ScalarType),
+    List(String, ScalarType, ScalarType), // name, count type, item type
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Source of property values from the body of a file
+trait ValueSource {
+    fn next(&mut self, ty: ScalarType) -> Result<f64>;
+}
+
+struct AsciiSource<'a> {
+    tokens: std::str::SplitAsciiWhitespace<'a>,
+}
+impl ValueSource for AsciiSource<'_> {
+    fn next(&mut self, _ty: ScalarType) -> Result<f64> {
+        let token = self.tokens.next().ok_or_else(|| anyhow!("unexpected end of file"))?;
+        token.parse().map_err(|_| anyhow!("invalid number {token}"))
+    }
+}
+
+struct BinarySource<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+impl ValueSource for BinarySource<'_> {
+    fn next(&mut self, ty: ScalarType) -> Result<f64> {
+        let size = ty.size();
+        if self.data.len() < size {
+            bail!("unexpected end of file");
+        }
+        let (bytes, rest) = self.data.split_at(size);
+        self.data = rest;
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(bytes);
+        if self.big_endian {
+            buf[..size].reverse();
+        }
+        let value = match ty {
+            ScalarType::Int8 => buf[0] as i8 as f64,
+            ScalarType::Uint8 => buf[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::Uint16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::Int32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::Uint32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::Float32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::Float64 => f64::from_le_bytes(buf),
+        };
+        Ok(value)
+    }
+}
+
+/// Vertex attribute a property is stored into
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Role {
+    Position(usize),
+    Normal(usize),
+    Uv(usize),
+    Color(usize),
+    Ignored,
+}
+
+fn role(name: &str) -> Role {
+    match name {
+        "x" => Role::Position(0),
+        "y" => Role::Position(1),
+        "z" => Role::Position(2),
+        "nx" => Role::Normal(0),
+        "ny" => Role::Normal(1),
+        "nz" => Role::Normal(2),
+        "u" | "s" | "texture_u" | "texture_s" => Role::Uv(0),
+        "v" | "t" | "texture_v" | "texture_t" => Role::Uv(1),
+        "red" | "r" => Role::Color(0),
+        "green" | "g" => Role::Color(1),
+        "blue" | "b" => Role::Color(2),
+        _ => Role::Ignored,
+    }
+}
+
+/// Load PLY file
+pub fn load_ply(path: impl AsRef<Path>) -> Result<MeshData> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    read_ply(&data).with_context(|| format!("failed to load {}", path.display()))
+}
+
+/// Parse the contents of a PLY file
+pub fn read_ply(data: &[u8]) -> Result<MeshData> {
+    let (encoding, elements, body) = read_header(data)?;
+    match encoding {
+        Encoding::Ascii => {
+            let body = std::str::from_utf8(body).context("ASCII body is not valid text")?;
+            read_body(&elements, &mut AsciiSource { tokens: body.split_ascii_whitespace() })
+        }
+        Encoding::BinaryLittleEndian => read_body(&elements, &mut BinarySource { data: body, big_endian: false }),
+        Encoding::BinaryBigEndian => read_body(&elements, &mut BinarySource { data: body, big_endian: true }),
+    }
+}
+
+/// Parse header, returning the body encoding, elements and the bytes after the header
+fn read_header(data: &[u8]) -> Result<(Encoding, Vec<Element>, &[u8])> {
+    let mut rest = data;
+    let mut next_line = || -> Option<&str> {
+        let end = rest.iter().position(|b| *b == b'\n')?;
+        let (line, tail) = rest.split_at(end);
+        rest = &tail[1..];
+        std::str::from_utf8(line).ok().map(|l| l.trim_end_matches('\r'))
+    };
+    if next_line() != Some("ply") {
+        bail!("not a PLY file, missing ply magic number");
+    }
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut n = 1;
+    loop {
+        n += 1;
+        let line = next_line().ok_or_else(|| anyhow!("header ends without end_header"))?;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let mut parse_line = || -> Result<bool> {
+            match tokens.as_slice() {
+                ["end_header"] => return Ok(true),
+                ["format", format, _version] => {
+                    encoding = Some(match *format {
+                        "ascii" => Encoding::Ascii,
+                        "binary_little_endian" => Encoding::BinaryLittleEndian,
+                        "binary_big_endian" => Encoding::BinaryBigEndian,
+                        other => bail!("unknown format {other}"),
+                    });
+                }
+                ["element", name, count] => {
+                    let count = count.parse().map_err(|_| anyhow!("invalid element count {count}"))?;
+                    elements.push(Element { name: name.to_string(), count, properties: Vec::new() });
+                }
+                ["property", "list", count_type, item_type, name] => {
+                    let element = elements.last_mut().ok_or_else(|| anyhow!("property before element"))?;
+                    let property = Property::List(
+                        name.to_string(),
+                        ScalarType::parse(count_type)?,
+                        ScalarType::parse(item_type)?
+                    );
+                    element.properties.push(property);
+                }
+                ["property", ty, name] => {
+                    let element = elements.last_mut().ok_or_else(|| anyhow!("property before element"))?;
+                    element.properties.push(Property::Scalar(name.to_string(), ScalarType::parse(ty)?));
+                }
+                ["comment", ..] | ["obj_info", ..] | [] => (),
+                _ => bail!("unrecognised header line"),
+            }
+            Ok(false)
+        };
+        let done = parse_line().with_context(|| format!("header line {n}: {line}"))?;
+        if done {
+            break;
+        }
+    }
+    let encoding = encoding.ok_or_else(|| anyhow!("header has no format line"))?;
+    Ok((encoding, elements, rest))
+}
+
+/// Read every element in order, keeping vertices and faces
+fn read_body(elements: &[Element], source: &mut impl ValueSource) -> Result<MeshData> {
+    let mut mesh = MeshData::default();
+    for element in elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(element, source, &mut mesh)?,
+            "face" => read_faces(element, source, &mut mesh)?,
+            _ => {
+                for i in 0..element.count {
+                    skip_element(element, source).with_context(|| format!("{} {i}", element.name))?;
+                }
+            }
+        }
+    }
+    let vertex_count = mesh.vertices.positions.len();
+    if let Some(index) = mesh.triangles.iter().flatten().find(|i| (**i as usize) >= vertex_count) {
+        bail!("face references vertex {index} but only {vertex_count} vertices are defined");
+    }
+    Ok(mesh)
+}
+
+fn read_vertices(element: &Element, source: &mut impl ValueSource, mesh: &mut MeshData) -> Result<()> {
+    let roles: Vec<Role> = element
+        .properties
+        .iter()
+        .map(|p| match p {
+            Property::Scalar(name, _) => role(name),
+            Property::List(..) => Role::Ignored,
+        })
+        .collect();
+    // attributes are only kept when all their components are present
+    let has = |role: fn(usize) -> Role, components: usize| {
+        (0..components).all(|k| roles.contains(&role(k)))
+    };
+    if !has(Role::Position, 3) {
+        bail!("vertex element needs x, y and z properties");
+    }
+    let has_normals = has(Role::Normal, 3);
+    let has_uvs = has(Role::Uv, 2);
+    let has_colors = has(Role::Color, 3);
+
+    let vertices = &mut mesh.vertices;
+    vertices.positions.reserve(element.count);
+    for i in 0..element.count {
+        let (mut position, mut normal, mut uv, mut color) = ([0.0; 3], [0.0; 3], [0.0; 2], [0.0; 3]);
+        let mut read_vertex = || -> Result<()> {
+            for (property, role) in element.properties.iter().zip(&roles) {
+                match (property, role) {
+                    (Property::Scalar(_, ty), Role::Position(k)) => position[*k] = source.next(*ty)?,
+                    (Property::Scalar(_, ty), Role::Normal(k)) => normal[*k] = source.next(*ty)?,
+                    (Property::Scalar(_, ty), Role::Uv(k)) => uv[*k] = source.next(*ty)?,
+                    (Property::Scalar(_, ty), Role::Color(k)) => color[*k] = source.next(*ty)? * ty.color_scale(),
+                    _ => skip_property(property, source)?,
+                }
+            }
+            Ok(())
+        };
+        read_vertex().with_context(|| format!("vertex {i}"))?;
+        vertices.positions.push(Point3::new(position[0], position[1], position[2]));
+        if has_normals {
+            vertices.normals.push(Vec3::new(normal[0], normal[1], normal[2]));
+        }
+        if has_uvs {
+            vertices.uvs.push((uv[0], uv[1]));
+        }
+        if has_colors {
+            vertices.colors.push(Color::new(color[0], color[1], color[2]));
+        }
+    }
+    Ok(())
+}
+
+fn read_faces(element: &Element, source: &mut impl ValueSource, mesh: &mut MeshData) -> Result<()> {
+    let is_index_list = |p: &Property| {
+        matches!(p, Property::List(name, ..) if name == "vertex_indices" || name == "vertex_index")
+    };
+    if !element.properties.iter().any(is_index_list) {
+        bail!("face element has no vertex_indices property");
+    }
+    mesh.triangles.reserve(element.count);
+    let mut indices = Vec::new();
+    for i in 0..element.count {
+        let mut read_face = || -> Result<()> {
+            for property in &element.properties {
+                match property {
+                    Property::List(_, count_type, item_type) if is_index_list(property) => {
+                        let count = source.next(*count_type)? as usize;
+                        if count < 3 {
+                            bail!("face needs at least 3 vertices, found {count}");
+                        }
+                        indices.clear();
+                        for _ in 0..count {
+                            let index = source.next(*item_type)?;
+                            if index < 0.0 {
+                                bail!("negative vertex index {index}");
+                            }
+                            indices.push(index as u32);
+                        }
+                        for k in 1..count - 1 {
+                            mesh.triangles.push([indices[0], indices[k], indices[k + 1]]);
+                        }
+                    }
+                    _ => skip_property(property, source)?,
+                }
+            }
+            Ok(())
+        };
+        read_face().with_context(|| format!("face {i}"))?;
+    }
+    Ok(())
+}
+
+fn skip_element(element: &Element, source: &mut impl ValueSource) -> Result<()> {
+    for property in &element.properties {
+        skip_property(property, source)?;
+    }
+    Ok(())
+}
+
+fn skip_property(property: &Property, source: &mut impl ValueSource) -> Result<()> {
+    match property {
+        Property::Scalar(_, ty) => {
+            source.next(*ty)?;
+        }
+        Property::List(_, count_type, item_type) => {
+            let count = source.next(*count_type)? as usize;
+            for _ in 0..count {
+                source.next(*item_type)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_ascii_and_binary_match() {
+    let ascii = "ply
+format ascii 1.0
+comment unit square with colored corners
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+";
+    let mesh = read_ply(ascii.as_bytes()).unwrap();
+    assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    assert_eq!(mesh.vertices.positions[2], Point3::new(1.0, 1.0, 0.0));
+    assert_eq!(mesh.vertices.colors[1], Color::new(0.0, 1.0, 0.0));
+    assert!(mesh.vertices.normals.is_empty());
+
+    // same mesh little endian, with an extra element to skip
+    let mut binary = b"ply
+format binary_little_endian 1.0
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element edge 1
+property int vertex1
+property int vertex2
+element face 1
+property list uchar int vertex_indices
+end_header
+".to_vec();
+    for (p, c) in [([0f32, 0.0, 0.0], [255u8, 0, 0]), ([1.0, 0.0, 0.0], [0, 255, 0]), ([1.0, 1.0, 0.0], [0, 0, 255]), ([0.0, 1.0, 0.0], [255, 255, 255])] {
+        p.iter().for_each(|x| binary.extend(x.to_le_bytes()));
+        binary.extend(c);
+    }
+    binary.extend(0i32.to_le_bytes());
+    binary.extend(1i32.to_le_bytes());
+    binary.push(4);
+    [0i32, 1, 2, 3].iter().for_each(|i| binary.extend(i.to_le_bytes()));
+    let binary_mesh = read_ply(&binary).unwrap();
+    assert_eq!(binary_mesh.triangles, mesh.triangles);
+    assert_eq!(binary_mesh.vertices.positions, mesh.vertices.positions);
+    assert_eq!(binary_mesh.vertices.colors, mesh.vertices.colors);
+
+    // truncated body and bad indices are reported
+    assert!(read_ply(&binary[..binary.len() - 2]).is_err());
+    let bad = ascii.replace("4 0 1 2 3", "3 0 1 9");
+    let err = format!("{:#}", read_ply(bad.as_bytes()).err().unwrap());
+    assert!(err.contains("vertex 9"), "{err}");
+}
@@ -0,0 +1,152 @@
+//! STL loader
+//!
+//! Reads binary and ASCII files. STL stores each triangle's corners separately, so corners
+//! at identical positions are merged into shared vertices. Facet normals are ignored in favour
+//! of the winding order, which STL requires to agree with them.
+
+use anyhow::{ Context, Result, anyhow, bail };
+use std::collections::HashMap;
+use std::path::Path;
+use crate::vec3::Point3;
+use crate::loader::MeshData;
+
+/// Bytes before the triangles of a binary file: 80 byte header and u32 triangle count
+const BINARY_HEADER_SIZE: usize = 84;
+/// Bytes per binary triangle: normal, three vertices and an attribute byte count
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+/// Load STL file
+pub fn load_stl(path: impl AsRef<Path>) -> Result<MeshData> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    read_stl(&data).with_context(|| format!("failed to load {}", path.display()))
+}
+
+/// Parse the contents of an STL file.
+///
+/// Binary files may also start with `solid`, so a file is only read as ASCII when its size
+/// doesn't match the triangle count of a binary file.
+pub fn read_stl(data: &[u8]) -> Result<MeshData> {
+    if data.len() >= BINARY_HEADER_SIZE {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if data.len() == BINARY_HEADER_SIZE + count * BINARY_TRIANGLE_SIZE {
+            return Ok(read_binary(&data[BINARY_HEADER_SIZE..], count));
+        }
+    }
+    if data.starts_with(b"solid") {
+        let source = std::str::from_utf8(data).context("ASCII STL is not valid text")?;
+        return read_ascii(source);
+    }
+    bail!("not an STL file, size doesn't match binary triangle count and no solid keyword");
+}
+
+fn read_binary(data: &[u8], count: usize) -> MeshData {
+    let mut builder = VertexMerger::with_capacity(count);
+    for triangle in data.chunks_exact(BINARY_TRIANGLE_SIZE) {
+        let float = |offset: usize| {
+            let b = &triangle[offset..offset + 4];
+            f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64
+        };
+        // skip the 12 byte normal
+        let corners = [12, 24, 36].map(|o| Point3::new(float(o), float(o + 4), float(o + 8)));
+        builder.add_triangle(corners);
+    }
+    builder.mesh
+}
+
+fn read_ascii(source: &str) -> Result<MeshData> {
+    let mut builder = VertexMerger::with_capacity(0);
+    let mut corners = Vec::with_capacity(3);
+    for (n, line) in source.lines().enumerate() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let mut parse_line = || -> Result<()> {
+            match tokens.as_slice() {
+                ["vertex", x, y, z] => {
+                    let parse = |t: &str| t.parse::<f64>().map_err(|_| anyhow!("invalid number {t}"));
+                    if corners.len() == 3 {
+                        bail!("facet has more than 3 vertices");
+                    }
+                    corners.push(Point3::new(parse(x)?, parse(y)?, parse(z)?));
+                }
+                ["vertex", ..] => bail!("vertex needs 3 coordinates"),
+                ["endloop"] => {
+                    if corners.len() != 3 {
+                        bail!("facet has {} vertices, expected 3", corners.len());
+                    }
+                    builder.add_triangle([corners[0], corners[1], corners[2]]);
+                    corners.clear();
+                }
+                _ => (),
+            }
+            Ok(())
+        };
+        parse_line().with_context(|| format!("line {}: {}", n + 1, line.trim()))?;
+    }
+    Ok(builder.mesh)
+}
+
+/// Builds mesh data, merging corners at exactly the same position
+struct VertexMerger {
+    mesh: MeshData,
+    indices: HashMap<[u64; 3], u32>,
+}
+
+impl VertexMerger {
+    fn with_capacity(triangles: usize) -> Self {
+        let mut mesh = MeshData::default();
+        mesh.triangles.reserve(triangles);
+        // closed meshes have about half as many vertices as triangles
+        mesh.vertices.positions.reserve(triangles / 2);
+        VertexMerger { mesh, indices: HashMap::with_capacity(triangles / 2) }
+    }
+    fn add_triangle(&mut self, corners: [Point3; 3]) {
+        let triangle = corners.map(|p| {
+            let positions = &mut self.mesh.vertices.positions;
+            *self.indices.entry([p.x.to_bits(), p.y.to_bits(), p.z.to_bits()]).or_insert_with(|| {
+                positions.push(p);
+                (positions.len() - 1) as u32
+            })
+        });
+        self.mesh.triangles.push(triangle);
+    }
+}
+
+#[test]
+fn test_binary_and_ascii() {
+    let ascii = "solid tetra
+facet normal 0 0 -1
+  outer loop
+    vertex 0 0 0
+    vertex 0 1 0
+    vertex 1 0 0
+  endloop
+endfacet
+facet normal 0 -1 0
+  outer loop
+    vertex 0 0 0
+    vertex 1 0 0
+    vertex 0 0 1
+  endloop
+endfacet
+endsolid tetra
+";
+    let mesh = read_stl(ascii.as_bytes()).unwrap();
+    assert_eq!(mesh.vertices.positions.len(), 4);
+    assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+
+    // binary header deliberately starts with solid too
+    let mut binary = b"solid but binary".to_vec();
+    binary.resize(80, 0);
+    binary.extend(2u32.to_le_bytes());
+    for corners in [[[0f32, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]], [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]]] {
+        binary.extend([0u8; 12]);
+        corners.iter().flatten().for_each(|x| binary.extend(x.to_le_bytes()));
+        binary.extend([0u8; 2]);
+    }
+    let binary_mesh = read_stl(&binary).unwrap();
+    assert_eq!(binary_mesh.vertices.positions, mesh.vertices.positions);
+    assert_eq!(binary_mesh.triangles, mesh.triangles);
+
+    let err = format!("{:#}", read_stl(ascii.replace("vertex 0 0 1", "vertex 0 0").as_bytes()).err().unwrap());
+    assert!(err.starts_with("line 13"), "{err}");
+}
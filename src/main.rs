@@ -1,6 +1,10 @@
 //! Render an image using ray tracing
+//!
+//! Renders one of the built-in scenes, or a scene file (see `raytracing::scene`), and writes
+//! the image to stdout or a file. Exits with 1 when rendering or writing fails and with 2 on
+//! bad arguments.
 
-use raytracing::vec3::{ Point3, Vec3 };
+use raytracing::vec3::{ Mat4, Point3, Vec3 };
 use raytracing::hittable::hittable_list::HittableList;
 use raytracing::hittable::sphere::Sphere;
 use raytracing::camera::CameraArgs;
@@ -8,9 +12,11 @@ use raytracing::material::lambertian::Lambertian;
 use raytracing::material::metal::Metal;
 use raytracing::color::Color;
 use raytracing::material::dialectric::Dialectric;
-use raytracing::random_float;
+use raytracing::rng::Pcg32;
+use std::io::{ BufWriter, Write };
+use std::path::{ Path, PathBuf };
+use std::process::ExitCode;
 use std::time::Instant;
-use raytracing::hittable::bvh::BvhNode;
 use raytracing::hittable::quad::Quad;
 use raytracing::texture::{
     SolidColor,
@@ -19,20 +25,270 @@ use raytracing::texture::{
     noise::NoiseTexture,
 };
 use raytracing::material::diffuse_light::DiffuseLight;
-use anyhow::Result;
+use anyhow::{ Context, Result, anyhow, bail };
 use raytracing::hittable::cube::Cube;
+use raytracing::hittable::instance::Instance;
+use raytracing::hittable::constant_medium::ConstantMedium;
 
 use raytracing::image::Image;
+use raytracing::output::{ self, OutputFormat };
+use raytracing::scene::{ self, Scene };
+use raytracing::tonemap::{ DisplayPipeline, ToneMap, Transfer };
 
-fn main() -> Result<()> {
+const USAGE: &str = "\
+Usage: raytracing [OPTIONS] [SCENE]
+
+SCENE is the name of a built-in scene or the path of a scene file, cornell_box by default.
+
+Options:
+  -w, --width <PIXELS>      image width, keeping the scene's aspect ratio
+  -s, --spp <N>             samples per pixel
+  -d, --max-depth <N>       maximum number of ray bounces
+  -t, --threads <N>         number of render threads
+      --seed <N>            random seed, the same seed renders the same image on any thread count
+  -o, --output <PATH>       write image to PATH instead of stdout
+  -f, --format <FORMAT>     png, jpg, ppm, ppm-ascii, hdr, pfm, exr or exr-float; defaults to the
+                            output file extension, or ppm-ascii on stdout
+      --tonemap <OP>        clamp, reinhard, extended-reinhard[:WHITE], hable or aces
+      --transfer <FN>       srgb, gamma2.2 (or another exponent) or linear
+      --exposure <STOPS>    exposure adjustment before tone mapping
+  -q, --quiet               no progress or timing on stderr
+      --list-scenes         list the built-in scenes
+  -h, --help                show this message";
+
+/// Scene compiled into the binary
+struct BuiltinScene {
+    name: &'static str,
+    description: &'static str,
+    build: fn(&mut Pcg32) -> Result<Scene>,
+}
+
+/// Random stream of the seed used to generate scenes, kept apart from the camera's per-pixel
+/// streams
+const SCENE_STREAM: u64 = u64::MAX;
+
+/// Display for scenes lit by small, bright lights. A plain clamp blows the lights and the
+/// surfaces next to them out to flat white, the filmic curve rolls them off gradually.
+const BRIGHT_LIGHT_DISPLAY: DisplayPipeline = DisplayPipeline {
+    exposure: 0.0,
+    tone_map: ToneMap::AcesFitted,
+    transfer: Transfer::Srgb,
+};
+
+const SCENES: &[BuiltinScene] = &[
+    BuiltinScene {
+        name: "cornell_box",
+        description: "Cornell box with two rotated blocks",
+        build: cornell_box,
+    },
+    BuiltinScene {
+        name: "cornell_smoke",
+        description: "Cornell box with blocks of black and white smoke",
+        build: cornell_smoke,
+    },
+    BuiltinScene {
+        name: "simple_light",
+        description: "Marble spheres lit by a quad and a sphere light",
+        build: simple_light,
+    },
+    BuiltinScene {
+        name: "quads",
+        description: "Five coloured quads around the camera",
+        build: quads,
+    },
+    BuiltinScene {
+        name: "perlin_spheres",
+        description: "Marble textured spheres under a sky",
+        build: perlin_spheres,
+    },
+    BuiltinScene {
+        name: "earth",
+        description: "Image textured globe, needs earthmap.jpg in the working directory",
+        build: earth_texture,
+    },
+    BuiltinScene {
+        name: "checkered_spheres",
+        description: "Two checker textured spheres",
+        build: checkered_spheres,
+    },
+    BuiltinScene {
+        name: "bouncing_spheres",
+        description: "Random field of moving, metal and glass spheres",
+        build: bouncing_spheres,
+    },
+];
+
+/// What the command line asks for
+#[derive(Debug, PartialEq)]
+enum Command {
+    Render(Options),
+    ListScenes,
+    Help,
+}
+
+/// Render settings from the command line, None where the scene's own value is kept
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    scene: Option<String>,
+    width: Option<i32>,
+    samples_per_pixel: Option<i32>,
+    max_depth: Option<i32>,
+    threads: Option<usize>,
+    seed: Option<u64>,
+    output: Option<PathBuf>,
+    format: Option<OutputFormat>,
+    tone_map: Option<ToneMap>,
+    transfer: Option<Transfer>,
+    exposure: Option<f64>,
+    quiet: bool,
+}
+
+fn main() -> ExitCode {
+    let command = match parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("error: {e:#}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let result = match command {
+        Command::Help => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        Command::ListScenes => {
+            let width = SCENES.iter().map(|s| s.name.len()).max().unwrap_or(0);
+            for scene in SCENES {
+                println!("{:width$}  {}", scene.name, scene.description);
+            }
+            Ok(())
+        }
+        Command::Render(options) => run(options),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Build the scene, apply overrides, render and write the image
+fn run(options: Options) -> Result<()> {
     let start = Instant::now();
-    //checkered_spheres();
-    cornell_box()?;
-    eprintln!("Took {} Seconds", start.elapsed().as_secs());
+    let name = options.scene.as_deref().unwrap_or("cornell_box");
+    // randomly generated scenes follow the seed too
+    let mut rng = Pcg32::new(options.seed.unwrap_or(0), SCENE_STREAM);
+    let mut scene = match SCENES.iter().find(|s| s.name == name) {
+        Some(builtin) => (builtin.build)(&mut rng)?,
+        None => scene::load_scene(name)?,
+    };
+
+    let camera = &mut scene.camera;
+    if let Some(width) = options.width {
+        camera.image_width = width;
+    }
+    if let Some(samples_per_pixel) = options.samples_per_pixel {
+        camera.samples_per_pixel = samples_per_pixel;
+    }
+    if let Some(max_depth) = options.max_depth {
+        camera.max_depth = max_depth;
+    }
+    if let Some(threads) = options.threads {
+        camera.threads = threads;
+    }
+    if let Some(seed) = options.seed {
+        camera.seed = seed;
+    }
+    camera.progress = !options.quiet;
+
+    let display = &mut scene.display;
+    if let Some(tone_map) = options.tone_map {
+        display.tone_map = tone_map;
+    }
+    if let Some(transfer) = options.transfer {
+        display.transfer = transfer;
+    }
+    if let Some(exposure) = options.exposure {
+        display.exposure = exposure;
+    }
+    let display = scene.display;
+    let framebuffer = scene.render();
+    match &options.output {
+        Some(path) => {
+            let format = match options.format {
+                Some(format) => format,
+                None => OutputFormat::from_path(path)?,
+            };
+            output::save_as(&framebuffer, path, format, &display)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        None => {
+            let mut out = BufWriter::new(std::io::stdout().lock());
+            output::write(&framebuffer, &mut out, options.format.unwrap_or(OutputFormat::PpmAscii), &display)?;
+            out.flush()?;
+        }
+    }
+    if !options.quiet {
+        eprintln!("Took {:.1} seconds", start.elapsed().as_secs_f64());
+    }
     Ok(())
 }
 
-fn cornell_box() -> Result<()> {
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // accept both --flag value and --flag=value
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline_value.clone().or_else(|| args.next()).ok_or_else(|| anyhow!("{flag} needs a value"))
+        };
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--list-scenes" => return Ok(Command::ListScenes),
+            "-w" | "--width" => options.width = Some(parse_positive(&flag, &value()?)?),
+            "-s" | "--spp" => options.samples_per_pixel = Some(parse_positive(&flag, &value()?)?),
+            "-d" | "--max-depth" => options.max_depth = Some(parse_positive(&flag, &value()?)?),
+            "-t" | "--threads" => options.threads = Some(parse_positive::<usize>(&flag, &value()?)?),
+            "--seed" => {
+                let value = value()?;
+                options.seed = Some(value.parse().map_err(|_| anyhow!("{flag}: {value} is not a non-negative integer"))?);
+            }
+            "-o" | "--output" => options.output = Some(PathBuf::from(value()?)),
+            "-f" | "--format" => options.format = Some(OutputFormat::from_name(&value()?)?),
+            "--tonemap" => options.tone_map = Some(ToneMap::from_name(&value()?)?),
+            "--transfer" => options.transfer = Some(Transfer::from_name(&value()?)?),
+            "--exposure" => {
+                let value = value()?;
+                options.exposure = Some(value.parse().map_err(|_| anyhow!("{flag}: {value} is not a number"))?);
+            }
+            "-q" | "--quiet" => options.quiet = true,
+            _ if flag.starts_with('-') && flag.len() > 1 => bail!("unknown option {flag}"),
+            _ if options.scene.is_some() => bail!("unexpected argument {arg}, only one scene can be given"),
+            _ => {
+                if !SCENES.iter().any(|s| s.name == arg) && !Path::new(&arg).exists() {
+                    bail!("no built-in scene or scene file named {arg}, see --list-scenes");
+                }
+                options.scene = Some(arg);
+            }
+        }
+    }
+    Ok(Command::Render(options))
+}
+
+fn parse_positive<T: std::str::FromStr + PartialOrd + Default>(flag: &str, value: &str) -> Result<T> {
+    match value.parse::<T>() {
+        Ok(n) if n > T::default() => Ok(n),
+        _ => bail!("{flag}: expected a positive integer, got {value}"),
+    }
+}
+
+fn cornell_box(_rng: &mut Pcg32) -> Result<Scene> {
     let mut world = HittableList::new();
     let red = Lambertian::new(SolidColor::new(Color::new(0.65, 0.05, 0.05)));
     let white = Lambertian::new(SolidColor::new(Color::new(0.73, 0.73, 0.73)));
@@ -56,20 +312,21 @@ fn cornell_box() -> Result<()> {
             red
         )
     );
-    world.add(
-        Quad::new_static(
-            Point3::new(343.0, 554.0, 332.0),
-            Vec3::new(-130.0, 0.0, 0.0),
-            Vec3::new(0.0, 0.0, -105.0),
-            light
-        )
+    let light_quad = Quad::new_static(
+        Point3::new(343.0, 554.0, 332.0),
+        Vec3::new(-130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -105.0),
+        light
     );
+    let mut lights = HittableList::new();
+    lights.add(light_quad.clone());
+    world.add(light_quad);
     world.add(
         Quad::new_static(
             Point3::new(0.0, 0.0, 0.0),
             Vec3::new(555.0, 0.0, 0.0),
             Vec3::new(0.0, 0.0, 555.0),
-            white.clone()
+            white
         )
     );
     world.add(
@@ -77,7 +334,7 @@ fn cornell_box() -> Result<()> {
             Point3::new(555.0, 555.0, 555.0),
             Vec3::new(-555.0, 0.0, 0.0),
             Vec3::new(0.0, 0.0, -555.0),
-            white.clone()
+            white
         )
     );
     world.add(
@@ -85,22 +342,23 @@ fn cornell_box() -> Result<()> {
             Point3::new(0.0, 0.0, 555.0),
             Vec3::new(555.0, 0.0, 0.0),
             Vec3::new(0.0, 555.0, 0.0),
-            white.clone()
+            white
         )
     );
 
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    let tall_box = Cube::from_points(Point3::zero(), Point3::new(165.0, 330.0, 165.0), white);
     world.add(
-        Cube::from_points(
-            Point3::new(130.0, 0.0, 65.0),
-            Point3::new(295.0, 165.0, 230.0),
-            white.clone()
+        Instance::new(
+            tall_box,
+            Mat4::translation(Vec3::new(265.0, 0.0, 295.0)) * Mat4::rotation(&up, 15.0)
         )
     );
+    let short_box = Cube::from_points(Point3::zero(), Point3::new(165.0, 165.0, 165.0), white);
     world.add(
-        Cube::from_points(
-            Point3::new(265.0, 0.0, 295.0),
-            Point3::new(430.0, 330.0, 460.0),
-            white.clone()
+        Instance::new(
+            short_box,
+            Mat4::translation(Vec3::new(130.0, 0.0, 65.0)) * Mat4::rotation(&up, -18.0)
         )
     );
 
@@ -128,12 +386,109 @@ fn cornell_box() -> Result<()> {
         vup,
         defocus_angle,
         focus_dist
-    ).initialize();
-    camera.render(world);
-    Ok(())
+    );
+    Ok(Scene { world, lights, camera, display: BRIGHT_LIGHT_DISPLAY })
+}
+
+fn cornell_smoke(_rng: &mut Pcg32) -> Result<Scene> {
+    let mut world = HittableList::new();
+    let red = Lambertian::new(SolidColor::new(Color::new(0.65, 0.05, 0.05)));
+    let white = Lambertian::new(SolidColor::new(Color::new(0.73, 0.73, 0.73)));
+    let green = Lambertian::new(SolidColor::new(Color::new(0.12, 0.45, 0.15)));
+    let light = DiffuseLight::new(SolidColor::new(Color::new(7.0, 7.0, 7.0)));
+
+    world.add(
+        Quad::new_static(
+            Point3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            green
+        )
+    );
+    world.add(
+        Quad::new_static(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            red
+        )
+    );
+    // larger, dimmer light so the smoke is lit evenly
+    let light_quad = Quad::new_static(
+        Point3::new(113.0, 554.0, 127.0),
+        Vec3::new(330.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 305.0),
+        light
+    );
+    let mut lights = HittableList::new();
+    lights.add(light_quad.clone());
+    world.add(light_quad);
+    world.add(
+        Quad::new_static(
+            Point3::new(0.0, 555.0, 0.0),
+            Vec3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            white
+        )
+    );
+    world.add(
+        Quad::new_static(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 555.0),
+            white
+        )
+    );
+    world.add(
+        Quad::new_static(
+            Point3::new(0.0, 0.0, 555.0),
+            Vec3::new(555.0, 0.0, 0.0),
+            Vec3::new(0.0, 555.0, 0.0),
+            white
+        )
+    );
+
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    let tall_box = Instance::new(
+        Cube::from_points(Point3::zero(), Point3::new(165.0, 330.0, 165.0), white),
+        Mat4::translation(Vec3::new(265.0, 0.0, 295.0)) * Mat4::rotation(&up, 15.0)
+    );
+    world.add(ConstantMedium::new(tall_box, 0.01, SolidColor::new(Color::new(0.0, 0.0, 0.0))));
+    let short_box = Instance::new(
+        Cube::from_points(Point3::zero(), Point3::new(165.0, 165.0, 165.0), white),
+        Mat4::translation(Vec3::new(130.0, 0.0, 65.0)) * Mat4::rotation(&up, -18.0)
+    );
+    world.add(ConstantMedium::new(short_box, 0.01, SolidColor::new(Color::new(1.0, 1.0, 1.0))));
+
+    let aspect_ratio = 1.0;
+    let image_width = 600;
+    let samples_per_pixel = 200;
+    let max_depth = 50;
+    let background = Color::new(0.0, 0.0, 0.0);
+    let vfov = 40.0;
+    let lookfrom = Point3::new(278.0, 278.0, -800.0);
+    let lookat = Point3::new(278.0, 278.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let defocus_angle = 0.0;
+    let focus_dist = 10.0;
+
+    let camera = CameraArgs::new(
+        aspect_ratio,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        background,
+        vfov,
+        lookfrom,
+        lookat,
+        vup,
+        defocus_angle,
+        focus_dist
+    );
+    Ok(Scene { world, lights, camera, display: BRIGHT_LIGHT_DISPLAY })
 }
 
-fn simple_light() -> Result<()> {
+fn simple_light(_rng: &mut Pcg32) -> Result<Scene> {
     let mut world = HittableList::new();
     let pertext = NoiseTexture::new(4.0);
     world.add(
@@ -143,15 +498,18 @@ fn simple_light() -> Result<()> {
         Sphere::new_static(Point3::new(0.0, 2.0, 0.0), 2.0, Lambertian::new(pertext.clone()))
     );
     let difflight = DiffuseLight::new(SolidColor::new(Color::new(4.0, 4.0, 4.0)));
-    world.add(
-        Quad::new_static(
-            Point3::new(3.0, 1.0, -2.0),
-            Vec3::new(2.0, 0.0, 0.0),
-            Vec3::new(0.0, 2.0, 0.0),
-            difflight.clone()
-        )
+    let light_quad = Quad::new_static(
+        Point3::new(3.0, 1.0, -2.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 2.0, 0.0),
+        difflight
     );
-    world.add(Sphere::new_static(Point3::new(0.0, 7.0, 0.0), 2.0, difflight));
+    let light_sphere = Sphere::new_static(Point3::new(0.0, 7.0, 0.0), 2.0, difflight);
+    let mut lights = HittableList::new();
+    lights.add(light_quad.clone());
+    lights.add(light_sphere.clone());
+    world.add(light_quad);
+    world.add(light_sphere);
 
     let aspect_ratio = 16.0 / 9.0;
     let image_width = 400;
@@ -177,12 +535,11 @@ fn simple_light() -> Result<()> {
         vup,
         defocus_angle,
         focus_dist
-    ).initialize();
-    camera.render(world);
-    Ok(())
+    );
+    Ok(Scene { world, lights, camera, display: BRIGHT_LIGHT_DISPLAY })
 }
 
-fn quads() -> Result<()> {
+fn quads(_rng: &mut Pcg32) -> Result<Scene> {
     let mut world = HittableList::new();
     let left_red = Lambertian::new(SolidColor::new(Color::new(1.0, 0.2, 0.2)));
     let back_green = Lambertian::new(SolidColor::new(Color::new(0.2, 1.0, 0.2)));
@@ -255,12 +612,11 @@ fn quads() -> Result<()> {
         vup,
         defocus_angle,
         focus_dist
-    ).initialize();
-    camera.render(world);
-    Ok(())
+    );
+    Ok(Scene { world, lights: HittableList::new(), camera, display: DisplayPipeline::default() })
 }
 
-fn perlin_spheres() -> Result<()> {
+fn perlin_spheres(_rng: &mut Pcg32) -> Result<Scene> {
     let pertext = NoiseTexture::new(4.0);
     let mut world = HittableList::new();
     world.add(
@@ -291,16 +647,16 @@ fn perlin_spheres() -> Result<()> {
         vup,
         defocus_angle,
         focus_dist
-    ).initialize();
-    camera.render(world);
-    Ok(())
+    );
+    Ok(Scene { world, lights: HittableList::new(), camera, display: DisplayPipeline::default() })
 }
 
-fn earth_texture() -> Result<()> {
-    let earth_image = Image::from_file("earthmap.jpg")?;
+fn earth_texture(_rng: &mut Pcg32) -> Result<Scene> {
+    let earth_image = Image::from_file("earthmap.jpg").context("failed to load earthmap.jpg")?;
     let earth_texture = ImageTexture::new(earth_image);
     let earth_surface = Lambertian::new(earth_texture);
-    let globe = Sphere::new_static(Point3::zero(), 2.0, earth_surface);
+    let mut world = HittableList::new();
+    world.add(Sphere::new_static(Point3::zero(), 2.0, earth_surface));
 
     let aspect_ratio = 16.0 / 9.0;
     let image_width = 400;
@@ -326,12 +682,11 @@ fn earth_texture() -> Result<()> {
         vup,
         defocus_angle,
         focus_dist
-    ).initialize();
-    camera.render(globe);
-    Ok(())
+    );
+    Ok(Scene { world, lights: HittableList::new(), camera, display: DisplayPipeline::default() })
 }
 
-fn checkered_spheres() {
+fn checkered_spheres(_rng: &mut Pcg32) -> Result<Scene> {
     let aspect_ratio = 16.0 / 9.0;
     let image_width = 400;
     let samples_per_pixel = 100;
@@ -356,7 +711,7 @@ fn checkered_spheres() {
         vup,
         defocus_angle,
         focus_dist
-    ).initialize();
+    );
     // World
     let mut world = HittableList::new();
     let checker = CheckeredTexture::from_solids(
@@ -368,11 +723,10 @@ fn checkered_spheres() {
         Sphere::new_static(Point3::new(0.0, 10.0, 0.0), 10.0, Lambertian::new(checker.clone()))
     );
     world.add(Sphere::new_static(Point3::new(0.0, -10.0, 0.0), 10.0, Lambertian::new(checker)));
-    let bvh = BvhNode::from_list(&mut world.objects);
-    camera.render(bvh);
+    Ok(Scene { world, lights: HittableList::new(), camera, display: DisplayPipeline::default() })
 }
 
-fn bouncing_sphers() {
+fn bouncing_spheres(rng: &mut Pcg32) -> Result<Scene> {
     let aspect_ratio = 16.0 / 9.0;
     let image_width = 400;
     let samples_per_pixel = 100;
@@ -397,7 +751,7 @@ fn bouncing_sphers() {
         vup,
         defocus_angle,
         focus_dist
-    ).initialize();
+    );
     // World
     let mut world = HittableList::new();
     let checker = CheckeredTexture::from_solids(
@@ -409,37 +763,37 @@ fn bouncing_sphers() {
     world.add(Sphere::new_static(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_material));
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = random_float(0.0, 1.0);
+            let choose_mat = rng.float(0.0, 1.0);
             let center = Point3::new(
-                (a as f64) + 0.9 * random_float(0.0, 1.0),
+                (a as f64) + 0.9 * rng.float(0.0, 1.0),
                 0.2,
-                (b as f64) + 0.9 * random_float(0.0, 1.0)
+                (b as f64) + 0.9 * rng.float(0.0, 1.0)
             );
             if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 if choose_mat < 0.8 {
                     // 80% chance lambertian
                     let albedo =
                         Color::new(
-                            random_float(0.0, 1.0),
-                            random_float(0.0, 1.0),
-                            random_float(0.0, 1.0)
+                            rng.float(0.0, 1.0),
+                            rng.float(0.0, 1.0),
+                            rng.float(0.0, 1.0)
                         ) *
                         Color::new(
-                            random_float(0.0, 1.0),
-                            random_float(0.0, 1.0),
-                            random_float(0.0, 1.0)
+                            rng.float(0.0, 1.0),
+                            rng.float(0.0, 1.0),
+                            rng.float(0.0, 1.0)
                         );
                     let sphere_material = Lambertian::new(SolidColor::new(albedo));
-                    let center2 = center + Vec3::new(0.0, random_float(0.0, 0.5), 0.0);
+                    let center2 = center + Vec3::new(0.0, rng.float(0.0, 0.5), 0.0);
                     world.add(Sphere::new_moving(center, center2, 0.2, sphere_material));
                 } else if choose_mat < 0.95 {
                     // 15% chance metal
                     let albedo = Color::new(
-                        random_float(0.5, 1.0),
-                        random_float(0.5, 1.0),
-                        random_float(0.5, 1.0)
+                        rng.float(0.5, 1.0),
+                        rng.float(0.5, 1.0),
+                        rng.float(0.5, 1.0)
                     );
-                    let fuzz = random_float(0.0, 0.5);
+                    let fuzz = rng.float(0.0, 0.5);
                     let sphere_material = Metal::new(albedo, fuzz);
                     world.add(Sphere::new_static(center, 0.2, sphere_material));
                 } else {
@@ -458,6 +812,30 @@ fn bouncing_sphers() {
     world.add(Sphere::new_static(Point3::new(0.0, 1.0, 0.0), 1.0, material1));
     world.add(Sphere::new_static(Point3::new(-4.0, 1.0, 0.0), 1.0, material2));
     world.add(Sphere::new_static(Point3::new(4.0, 1.0, 0.0), 1.0, material3));
-    let bvh = BvhNode::from_list(&mut world.objects);
-    camera.render(bvh);
+    Ok(Scene { world, lights: HittableList::new(), camera, display: DisplayPipeline::default() })
+}
+
+#[test]
+fn test_parse_args() {
+    let args = |list: &[&str]| parse_args(list.iter().map(|s| s.to_string()));
+    assert_eq!(args(&[]).unwrap(), Command::Render(Options::default()));
+    assert_eq!(args(&["--list-scenes"]).unwrap(), Command::ListScenes);
+    let Command::Render(options) = args(&[
+        "quads", "-w", "200", "--spp=16", "--seed", "7", "-o", "out.png", "--tonemap", "extended-reinhard:2", "--transfer", "gamma2.2",
+    ]).unwrap() else {
+        panic!("expected render command");
+    };
+    assert_eq!(options.scene.as_deref(), Some("quads"));
+    assert_eq!(options.width, Some(200));
+    assert_eq!(options.samples_per_pixel, Some(16));
+    assert_eq!(options.seed, Some(7));
+    assert_eq!(options.output, Some(PathBuf::from("out.png")));
+    assert_eq!(options.tone_map, Some(ToneMap::ExtendedReinhard { white: 2.0 }));
+    assert_eq!(options.transfer, Some(Transfer::Gamma(2.2)));
+    assert!(args(&["--transfer", "gamma"]).is_err());
+    assert!(args(&["--width", "0"]).is_err());
+    assert!(args(&["--spp"]).is_err());
+    assert!(args(&["--bogus"]).is_err());
+    assert!(args(&["no_such_scene"]).is_err());
+    assert!(args(&["quads", "cornell_box"]).is_err());
 }
@@ -4,17 +4,66 @@ pub mod lambertian;
 pub mod metal;
 pub mod dialectric;
 pub mod diffuse_light;
+pub mod isotropic;
+pub mod henyey_greenstein;
 
 use crate::ray::Ray;
 use crate::hittable::HitRecord;
 use crate::color::Color;
-use crate::vec3::Point3;
+use crate::pdf::Pdf;
+use crate::rng::Pcg32;
+use std::sync::Arc;
 
-pub trait Material {
-    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Color)> {
+/// How light leaves a surface after scattering
+pub enum Scatter {
+    /// Single deterministic (or already randomised) outgoing ray, e.g. mirrors and glass.
+    /// No density is available so lights can't be sampled directly.
+    Specular(Ray),
+    /// Outgoing direction drawn from a distribution whose density is known
+    Diffuse(Box<dyn Pdf>),
+}
+
+/// Result of a material scattering a ray
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub scatter: Scatter,
+}
+impl ScatterRecord {
+    pub fn specular(attenuation: Color, ray: Ray) -> Self {
+        ScatterRecord { attenuation, scatter: Scatter::Specular(ray) }
+    }
+    pub fn diffuse(attenuation: Color, pdf: impl Pdf + 'static) -> Self {
+        ScatterRecord { attenuation, scatter: Scatter::Diffuse(Box::new(pdf)) }
+    }
+    pub fn is_specular(&self) -> bool {
+        matches!(self.scatter, Scatter::Specular(_))
+    }
+}
+
+pub trait Material: Send + Sync {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _rng: &mut Pcg32) -> Option<ScatterRecord> {
         None
     }
-    fn emit(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+    /// Density of the material scattering r_in into direction of `scattered`. Together with
+    /// the attenuation this gives the reflected light for directions not drawn from the
+    /// scatter record's pdf.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+    fn emit(&self, _r_in: &Ray, _rec: &HitRecord) -> Color {
         Color::new(0.0, 0.0, 0.0)
     }
 }
+
+/// Shared materials, e.g. loaded from a file and used by several objects
+impl<M: Material + ?Sized> Material for Arc<M> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut Pcg32) -> Option<ScatterRecord> {
+        (**self).scatter(r_in, rec, rng)
+    }
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        (**self).scattering_pdf(r_in, rec, scattered)
+    }
+    fn emit(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        (**self).emit(r_in, rec)
+    }
+}
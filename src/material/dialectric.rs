@@ -1,6 +1,5 @@
 //! Defines dialectric material
-use crate::material::{ Material, HitRecord, Ray, Color };
-use crate::random_float;
+use crate::material::{ Material, HitRecord, Ray, Color, ScatterRecord, Pcg32 };
 
 #[derive(Clone, Copy)]
 pub struct Dialectric {
@@ -12,7 +11,7 @@ impl Dialectric {
     }
 }
 impl Material for Dialectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut Pcg32) -> Option<ScatterRecord> {
         let attenuation = Color::new(1.0, 1.0, 1.0);
         let mut ri = self.refraction_index;
         if rec.front_face {
@@ -23,13 +22,13 @@ impl Material for Dialectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let direction = match
             ri * sin_theta > 1.0 ||
-            reflectance(cos_theta, ri) > random_float(0.0, 1.0)
+            reflectance(cos_theta, ri) > rng.next_f64()
         {
             true => unit_direction.reflect(&rec.normal),
             false => unit_direction.refract(&rec.normal, ri),
         };
         let scattered_ray = Ray::new(rec.p, direction, r_in.time);
-        Some((scattered_ray, attenuation))
+        Some(ScatterRecord::specular(attenuation, scattered_ray))
     }
 }
 /// Calculate reflectance
@@ -1,8 +1,7 @@
 //! Material that generates diffuse light
 
-use crate::material::Material;
+use crate::material::{ Material, HitRecord, Ray };
 use crate::color::Color;
-use crate::vec3::Point3;
 use crate::texture::Texture;
 
 #[derive(Debug, Clone, Copy)]
@@ -16,7 +15,11 @@ impl<T: Texture> DiffuseLight<T> {
     }
 }
 impl<T: Texture> Material for DiffuseLight<T> {
-    fn emit(&self, u: f64, v: f64, p: Point3) -> Color {
-        self.texture.color(u, v, p)
+    /// Light is only emitted from the front face, the side the surface normal points to
+    fn emit(&self, _r_in: &Ray, rec: &HitRecord) -> Color {
+        if !rec.front_face {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        self.texture.value(rec)
     }
 }
\ No newline at end of file
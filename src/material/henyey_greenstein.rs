@@ -0,0 +1,30 @@
+//! Defines Henyey-Greenstein material, an anisotropic phase function for participating media
+
+use crate::material::{ Material, HitRecord, Ray, ScatterRecord, Pcg32 };
+use crate::pdf::{ HenyeyGreensteinPdf, henyey_greenstein };
+use crate::texture::Texture;
+
+/// Anisotropy is kept inside (-1, 1), at the limits the phase function is a delta
+const MAX_ANISOTROPY: f64 = 0.999;
+
+/// Scatters around the direction of travel, forward for anisotropy g > 0, backward for g < 0
+/// and uniformly for g = 0 like `Isotropic`
+#[derive(Clone, Copy)]
+pub struct HenyeyGreenstein<T: Texture> {
+    texture: T,
+    g: f64,
+}
+impl<T: Texture> HenyeyGreenstein<T> {
+    pub fn new(texture: T, g: f64) -> Self {
+        HenyeyGreenstein { texture, g: g.clamp(-MAX_ANISOTROPY, MAX_ANISOTROPY) }
+    }
+}
+impl<T: Texture> Material for HenyeyGreenstein<T> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, _rng: &mut Pcg32) -> Option<ScatterRecord> {
+        Some(ScatterRecord::diffuse(self.texture.value(rec), HenyeyGreensteinPdf::new(&r_in.direction, self.g)))
+    }
+    fn scattering_pdf(&self, r_in: &Ray, _rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = r_in.direction.unit_vector().dot(&scattered.direction.unit_vector());
+        henyey_greenstein(cos_theta, self.g)
+    }
+}
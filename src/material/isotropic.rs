@@ -0,0 +1,25 @@
+//! Defines isotropic material, the phase function of participating media
+
+use crate::material::{ Material, HitRecord, Ray, ScatterRecord, Pcg32 };
+use crate::pdf::SpherePdf;
+use crate::texture::Texture;
+use std::f64::consts::PI;
+
+/// Scatters uniformly in all directions
+#[derive(Clone, Copy)]
+pub struct Isotropic<T: Texture> {
+    texture: T,
+}
+impl<T: Texture> Isotropic<T> {
+    pub fn new(texture: T) -> Self {
+        Isotropic { texture }
+    }
+}
+impl<T: Texture> Material for Isotropic<T> {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, _rng: &mut Pcg32) -> Option<ScatterRecord> {
+        Some(ScatterRecord::diffuse(self.texture.value(rec), SpherePdf))
+    }
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
@@ -1,8 +1,9 @@
 //! Defines lambertian material
 
-use crate::material::{ Material, HitRecord, Ray, Color };
+use crate::material::{ Material, HitRecord, Ray, ScatterRecord, Pcg32 };
+use crate::pdf::CosinePdf;
 use crate::texture::Texture;
-use crate::vec3::Vec3;
+use std::f64::consts::PI;
 
 
 #[derive(Clone, Copy)]
@@ -15,15 +16,12 @@ impl<T: Texture> Lambertian<T> {
     }
 }
 impl<T: Texture> Material for Lambertian<T> {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
-        let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
-
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
-        }
-
-        let scattered_ray = Ray::new(rec.p, scatter_direction, r_in.time);
-        let attenuation = self.texture.color(rec.u, rec.v, rec.p).to_owned();
-        Some((scattered_ray, attenuation))
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, _rng: &mut Pcg32) -> Option<ScatterRecord> {
+        let attenuation = self.texture.value(rec);
+        Some(ScatterRecord::diffuse(attenuation, CosinePdf::new(&rec.normal)))
+    }
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = rec.normal.dot(&scattered.direction.unit_vector());
+        f64::max(0.0, cos_theta / PI)
     }
 }
@@ -1,7 +1,9 @@
 //! Defines metal material
-use crate::material::{ Material, HitRecord, Ray, Color };
-use crate::vec3::Vec3;
+use crate::material::{ Material, HitRecord, Ray, Color, ScatterRecord, Pcg32 };
+use crate::pdf::{ Pdf, PhongPdf };
 
+/// Reflective metal. A fuzz of 0 is a perfect mirror, larger fuzz spreads reflections over a
+/// glossy lobe around the mirror direction.
 #[derive(Clone, Copy)]
 pub struct Metal {
     albedo: Color,
@@ -11,13 +13,26 @@ impl Metal {
     pub fn new(albedo: Color, fuzz: f64) -> Self {
         Metal { albedo, fuzz }
     }
+    /// Phong exponent of the glossy lobe equivalent to the fuzz
+    fn exponent(&self) -> f64 {
+        f64::max(0.0, 2.0 / (self.fuzz * self.fuzz) - 2.0)
+    }
 }
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
-        let mut reflected = r_in.direction.reflect(&rec.normal);
-        reflected = reflected.unit_vector() + self.fuzz * Vec3::random_unit_vector();
-        let scattered_ray = Ray::new(rec.p, reflected, r_in.time);
-        let attenuation = self.albedo;
-        Some((scattered_ray, attenuation))
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, _rng: &mut Pcg32) -> Option<ScatterRecord> {
+        let reflected = r_in.direction.unit_vector().reflect(&rec.normal);
+        if self.fuzz <= 0.0 {
+            let scattered_ray = Ray::new(rec.p, reflected, r_in.time);
+            return Some(ScatterRecord::specular(self.albedo, scattered_ray));
+        }
+        Some(ScatterRecord::diffuse(self.albedo, PhongPdf::new(&reflected, self.exponent())))
+    }
+    /// Lobe density, with directions below the surface absorbed
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        if self.fuzz <= 0.0 || scattered.direction.dot(&rec.normal) <= 0.0 {
+            return 0.0;
+        }
+        let reflected = r_in.direction.unit_vector().reflect(&rec.normal);
+        PhongPdf::new(&reflected, self.exponent()).value(scattered.direction)
     }
 }
@@ -0,0 +1,195 @@
+//! Save rendered framebuffers to image files
+//!
+//! 8 bit formats map pixels through a `DisplayPipeline`, whose default matches the gamma
+//! correction and clamping of `color::write_color`. High dynamic range formats ignore the
+//! pipeline and store the unclamped linear radiance.
+
+pub mod radiance;
+pub mod pfm;
+pub mod exr;
+
+use anyhow::{ Result, anyhow, bail };
+use image::codecs::jpeg::JpegEncoder;
+use image::{ ExtendedColorType, ImageFormat };
+use std::fs::File;
+use std::io::{ BufWriter, Write };
+use std::path::Path;
+use crate::color::write_display_color;
+use crate::framebuffer::Framebuffer;
+use crate::tonemap::DisplayPipeline;
+use crate::output::exr::{ ExrCompression, ExrPixelType, write_exr };
+use crate::output::pfm::write_pfm;
+use crate::output::radiance::write_hdr;
+
+/// Quality used when encoding JPEG files, from 1 to 100
+const JPEG_QUALITY: u8 = 90;
+
+/// Image file formats a framebuffer can be saved as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    Ppm, // Binary PPM (P6)
+    PpmAscii, // ASCII PPM (P3)
+    Hdr, // Radiance RGBE
+    Pfm, // Portable Float Map
+    Exr(ExrPixelType, ExrCompression), // Scanline OpenEXR
+}
+
+impl OutputFormat {
+    /// Pick format from file extension. `.ppm` files are written as binary P6 and `.exr`
+    /// files as ZIP compressed half floats.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .ok_or_else(|| anyhow!("no file extension on {}", path.display()))?;
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Ok(OutputFormat::Png),
+            "jpg" | "jpeg" => Ok(OutputFormat::Jpeg),
+            "ppm" => Ok(OutputFormat::Ppm),
+            "hdr" => Ok(OutputFormat::Hdr),
+            "pfm" => Ok(OutputFormat::Pfm),
+            "exr" => Ok(OutputFormat::Exr(ExrPixelType::Half, ExrCompression::Zip)),
+            other => bail!("unsupported output file extension .{other}"),
+        }
+    }
+    /// Pick format by name: the file extensions plus `ppm-ascii` for P3 and `exr-float`
+    /// for 32 bit OpenEXR
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ppm-ascii" => Ok(OutputFormat::PpmAscii),
+            "exr-float" => Ok(OutputFormat::Exr(ExrPixelType::Float, ExrCompression::Zip)),
+            other => {
+                OutputFormat::from_path(Path::new(&format!("image.{other}")))
+                    .map_err(|_| anyhow!("unknown output format {name}"))
+            }
+        }
+    }
+}
+
+/// Save framebuffer to path, picking the format from the file extension
+pub fn save(
+    framebuffer: &Framebuffer,
+    path: impl AsRef<Path>,
+    display: &DisplayPipeline
+) -> Result<()> {
+    let path = path.as_ref();
+    save_as(framebuffer, path, OutputFormat::from_path(path)?, display)
+}
+
+/// Save framebuffer to path in the given format
+pub fn save_as(
+    framebuffer: &Framebuffer,
+    path: impl AsRef<Path>,
+    format: OutputFormat,
+    display: &DisplayPipeline
+) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write(framebuffer, &mut out, format, display)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Encode framebuffer in the given format to an output stream
+pub fn write(
+    framebuffer: &Framebuffer,
+    mut out: impl Write,
+    format: OutputFormat,
+    display: &DisplayPipeline
+) -> Result<()> {
+    let (width, height) = (framebuffer.width as u32, framebuffer.height as u32);
+    match format {
+        OutputFormat::Png => {
+            // PNG encoder needs to seek, so encode into memory first
+            let mut encoded = std::io::Cursor::new(Vec::new());
+            image::write_buffer_with_format(
+                &mut encoded,
+                &to_rgb8(framebuffer, display),
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+                ImageFormat::Png
+            )?;
+            out.write_all(encoded.get_ref())?;
+        }
+        OutputFormat::Jpeg => {
+            let mut encoder = JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY);
+            encoder.encode(&to_rgb8(framebuffer, display), width, height, ExtendedColorType::Rgb8)?;
+        }
+        OutputFormat::Ppm => write_p6(framebuffer, &mut out, display)?,
+        OutputFormat::PpmAscii => write_p3(framebuffer, &mut out, display)?,
+        OutputFormat::Hdr => write_hdr(framebuffer, &mut out)?,
+        OutputFormat::Pfm => write_pfm(framebuffer, &mut out)?,
+        OutputFormat::Exr(pixel_type, compression) => {
+            write_exr(framebuffer, &mut out, pixel_type, compression)?
+        }
+    }
+    Ok(())
+}
+
+/// Write image as binary PPM (P6)
+pub fn write_p6(framebuffer: &Framebuffer, mut out: impl Write, display: &DisplayPipeline) -> Result<()> {
+    write!(out, "P6\n{} {}\n255\n", framebuffer.width, framebuffer.height)?;
+    out.write_all(&to_rgb8(framebuffer, display))?;
+    Ok(())
+}
+
+/// Write image as ASCII PPM (P3)
+pub fn write_p3(framebuffer: &Framebuffer, out: impl Write, display: &DisplayPipeline) -> Result<()> {
+    let mut out = BufWriter::new(out);
+    writeln!(out, "P3\n{} {}\n255", framebuffer.width, framebuffer.height)?;
+    for pixel_color in framebuffer.pixels() {
+        write_display_color(&mut out, *pixel_color, display)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Display mapped 8 bit r, g, b bytes of every pixel row by row
+fn to_rgb8(framebuffer: &Framebuffer, display: &DisplayPipeline) -> Vec<u8> {
+    framebuffer.pixels().iter().flat_map(|c| display.to_bytes(*c)).collect()
+}
+
+#[test]
+fn test_from_path() {
+    assert_eq!(OutputFormat::from_path(Path::new("out.PNG")).unwrap(), OutputFormat::Png);
+    assert_eq!(OutputFormat::from_path(Path::new("a/b.jpeg")).unwrap(), OutputFormat::Jpeg);
+    assert_eq!(OutputFormat::from_path(Path::new("render.ppm")).unwrap(), OutputFormat::Ppm);
+    assert_eq!(
+        OutputFormat::from_path(Path::new("render.exr")).unwrap(),
+        OutputFormat::Exr(ExrPixelType::Half, ExrCompression::Zip)
+    );
+    assert!(OutputFormat::from_path(Path::new("render.gif")).is_err());
+    assert!(OutputFormat::from_path(Path::new("render")).is_err());
+}
+
+#[test]
+fn test_p6_matches_p3() {
+    use crate::color::Color;
+    let mut fb = Framebuffer::new(2, 1);
+    fb.set_pixel(0, 0, Color::new(0.25, 4.0, -1.0), 1);
+    fb.set_pixel(1, 0, Color::new(0.5, 0.1, 0.9), 1);
+    let display = DisplayPipeline::default();
+    let mut p6 = Vec::new();
+    write(&fb, &mut p6, OutputFormat::Ppm, &display).unwrap();
+    let mut p3 = Vec::new();
+    write(&fb, &mut p3, OutputFormat::PpmAscii, &display).unwrap();
+    let p3 = String::from_utf8(p3).unwrap();
+    let p3_bytes: Vec<u8> = p3.split_whitespace().skip(4).map(|v| v.parse().unwrap()).collect();
+    assert_eq!(&p6[..11], b"P6\n2 1\n255\n");
+    assert_eq!(&p6[11..], &p3_bytes[..]);
+}
+
+#[test]
+fn test_png_round_trip() {
+    use crate::color::Color;
+    let mut fb = Framebuffer::new(3, 2);
+    fb.set_pixel(2, 1, Color::new(1.0, 0.25, 0.0), 1);
+    let display = DisplayPipeline::default();
+    let mut png = Vec::new();
+    write(&fb, &mut png, OutputFormat::Png, &display).unwrap();
+    let decoded = image::load_from_memory(&png).unwrap().to_rgb8();
+    assert_eq!(decoded.dimensions(), (3, 2));
+    assert_eq!(decoded.as_raw(), &to_rgb8(&fb, &display));
+}
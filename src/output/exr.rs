@@ -0,0 +1,201 @@
+//! Minimal single part scanline OpenEXR (.exr) writer
+//!
+//! Writes R, G, B channels as either half or full floats, uncompressed or ZIP compressed in
+//! blocks of 16 scanlines.
+
+use anyhow::Result;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use half::f16;
+use std::io::Write;
+use crate::framebuffer::Framebuffer;
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+/// File format version 2, single part scanline image
+const VERSION: [u8; 4] = [2, 0, 0, 0];
+
+/// Storage type of each channel sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExrPixelType {
+    Half,
+    Float,
+}
+impl ExrPixelType {
+    fn id(&self) -> i32 {
+        match self {
+            ExrPixelType::Half => 1,
+            ExrPixelType::Float => 2,
+        }
+    }
+    fn bytes(&self) -> usize {
+        match self {
+            ExrPixelType::Half => 2,
+            ExrPixelType::Float => 4,
+        }
+    }
+}
+
+/// Compression applied to each block of scanlines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExrCompression {
+    None,
+    Zip,
+}
+impl ExrCompression {
+    fn id(&self) -> u8 {
+        match self {
+            ExrCompression::None => 0,
+            ExrCompression::Zip => 3,
+        }
+    }
+    fn lines_per_block(&self) -> usize {
+        match self {
+            ExrCompression::None => 1,
+            ExrCompression::Zip => 16,
+        }
+    }
+}
+
+/// Write framebuffer as OpenEXR image
+pub fn write_exr(
+    framebuffer: &Framebuffer,
+    mut out: impl Write,
+    pixel_type: ExrPixelType,
+    compression: ExrCompression
+) -> Result<()> {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let mut header = Vec::new();
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&VERSION);
+
+    // channels are stored in alphabetical order
+    let mut channels = Vec::new();
+    for name in ["B", "G", "R"] {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&pixel_type.id().to_le_bytes());
+        channels.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+        channels.extend_from_slice(&1i32.to_le_bytes()); // x sampling
+        channels.extend_from_slice(&1i32.to_le_bytes()); // y sampling
+    }
+    channels.push(0);
+    let mut window = Vec::new();
+    for v in [0, 0, (width as i32) - 1, (height as i32) - 1] {
+        window.extend_from_slice(&v.to_le_bytes());
+    }
+    let mut screen_center = Vec::new();
+    screen_center.extend_from_slice(&0f32.to_le_bytes());
+    screen_center.extend_from_slice(&0f32.to_le_bytes());
+
+    write_attribute(&mut header, "channels", "chlist", &channels);
+    write_attribute(&mut header, "compression", "compression", &[compression.id()]);
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]); // increasing y
+    write_attribute(&mut header, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &screen_center);
+    write_attribute(&mut header, "screenWindowWidth", "float", &1f32.to_le_bytes());
+    header.push(0);
+
+    // encode every block before writing so the offset table can be filled in
+    let lines_per_block = compression.lines_per_block();
+    let mut blocks = Vec::new();
+    for first_line in (0..height).step_by(lines_per_block) {
+        let last_line = (first_line + lines_per_block).min(height);
+        let raw = block_data(framebuffer, first_line..last_line, pixel_type);
+        let data = match compression {
+            ExrCompression::None => raw,
+            ExrCompression::Zip => zip_compress(&raw)?,
+        };
+        blocks.push((first_line, data));
+    }
+
+    let mut offset = (header.len() + 8 * blocks.len()) as u64;
+    out.write_all(&header)?;
+    for (_, data) in &blocks {
+        out.write_all(&offset.to_le_bytes())?;
+        offset += 8 + data.len() as u64;
+    }
+    for (first_line, data) in &blocks {
+        out.write_all(&(*first_line as i32).to_le_bytes())?;
+        out.write_all(&(data.len() as i32).to_le_bytes())?;
+        out.write_all(data)?;
+    }
+    Ok(())
+}
+
+/// Append header attribute as name, type, size and value
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Uncompressed pixel data of a block. Each scanline holds all B samples, then G, then R.
+fn block_data(framebuffer: &Framebuffer, lines: std::ops::Range<usize>, pixel_type: ExrPixelType) -> Vec<u8> {
+    let width = framebuffer.width;
+    let mut data = Vec::with_capacity(lines.len() * width * 3 * pixel_type.bytes());
+    for j in lines {
+        let row = &framebuffer.pixels()[j * width..(j + 1) * width];
+        for channel in 0..3 {
+            for c in row {
+                let v = [c.b, c.g, c.r][channel] as f32;
+                match pixel_type {
+                    ExrPixelType::Half => data.extend_from_slice(&f16::from_f32(v).to_le_bytes()),
+                    ExrPixelType::Float => data.extend_from_slice(&v.to_le_bytes()),
+                }
+            }
+        }
+    }
+    data
+}
+
+/// Compress block the way OpenEXR's ZIP codec does: split even and odd bytes, delta encode,
+/// then deflate. Blocks that don't shrink are stored uncompressed.
+fn zip_compress(raw: &[u8]) -> Result<Vec<u8>> {
+    let half = raw.len().div_ceil(2);
+    let mut reordered = vec![0; raw.len()];
+    for (i, byte) in raw.iter().enumerate() {
+        let idx = if i % 2 == 0 { i / 2 } else { half + i / 2 };
+        reordered[idx] = *byte;
+    }
+    let mut prev = reordered.first().copied().unwrap_or(0);
+    for byte in reordered.iter_mut().skip(1) {
+        let current = *byte;
+        *byte = current.wrapping_sub(prev).wrapping_add(128);
+        prev = current;
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&reordered)?;
+    let compressed = encoder.finish()?;
+    match compressed.len() < raw.len() {
+        true => Ok(compressed),
+        false => Ok(raw.to_vec()),
+    }
+}
+
+#[test]
+fn test_exr_round_trip() {
+    use crate::color::Color;
+    let mut fb = Framebuffer::new(7, 19);
+    for j in 0..19 {
+        for i in 0..7 {
+            fb.set_pixel(i, j, Color::new(15.0, (i as f64) * 0.25, (j as f64) * 0.5), 1);
+        }
+    }
+    for pixel_type in [ExrPixelType::Half, ExrPixelType::Float] {
+        for compression in [ExrCompression::None, ExrCompression::Zip] {
+            let mut exr = Vec::new();
+            write_exr(&fb, &mut exr, pixel_type, compression).unwrap();
+            let decoded = image::load_from_memory(&exr).unwrap().to_rgb32f();
+            assert_eq!(decoded.dimensions(), (7, 19));
+            for (i, j, pixel) in decoded.enumerate_pixels() {
+                let expected = fb.pixel(i as usize, j as usize);
+                assert_eq!(pixel.0, [expected.r as f32, expected.g as f32, expected.b as f32]);
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Portable Float Map (.pfm) writer
+
+use anyhow::Result;
+use std::io::Write;
+use crate::framebuffer::Framebuffer;
+
+/// Write framebuffer as little endian 32 bit float RGB image
+///
+/// PFM stores scanlines from the bottom of the image to the top.
+pub fn write_pfm(framebuffer: &Framebuffer, mut out: impl Write) -> Result<()> {
+    // negative scale marks little endian data
+    write!(out, "PF\n{} {}\n-1.0\n", framebuffer.width, framebuffer.height)?;
+    for row in framebuffer.pixels().chunks(framebuffer.width.max(1)).rev() {
+        for c in row {
+            for v in [c.r, c.g, c.b] {
+                out.write_all(&(v as f32).to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_pfm_layout() {
+    use crate::color::Color;
+    let mut fb = Framebuffer::new(1, 2);
+    fb.set_pixel(0, 0, Color::new(15.0, 0.5, -1.0), 1);
+    let mut pfm = Vec::new();
+    write_pfm(&fb, &mut pfm).unwrap();
+    let header = b"PF\n1 2\n-1.0\n";
+    assert_eq!(&pfm[..header.len()], header);
+    let data: Vec<f32> = pfm[header.len()..]
+        .chunks(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    // bottom row first
+    assert_eq!(data, vec![0.0, 0.0, 0.0, 15.0, 0.5, -1.0]);
+}
@@ -0,0 +1,114 @@
+//! Radiance RGBE (.hdr) writer
+
+use anyhow::Result;
+use std::io::Write;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+/// Scanlines outside this width range can't be run length encoded
+const MIN_RLE_WIDTH: usize = 8;
+const MAX_RLE_WIDTH: usize = 0x7fff;
+/// Longest run a single RLE code byte can describe
+const MAX_RUN: usize = 127;
+
+/// Write framebuffer as Radiance RGBE image using new-style run length encoded scanlines
+pub fn write_hdr(framebuffer: &Framebuffer, mut out: impl Write) -> Result<()> {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    write!(out, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {height} +X {width}\n")?;
+
+    let mut scanline = Vec::with_capacity(width);
+    let mut channel = Vec::with_capacity(width);
+    for row in framebuffer.pixels().chunks(width.max(1)) {
+        scanline.clear();
+        scanline.extend(row.iter().map(|c| to_rgbe(*c)));
+        if !(MIN_RLE_WIDTH..=MAX_RLE_WIDTH).contains(&width) {
+            // flat scanline
+            for rgbe in &scanline {
+                out.write_all(rgbe)?;
+            }
+            continue;
+        }
+        out.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+        for c in 0..4 {
+            channel.clear();
+            channel.extend(scanline.iter().map(|rgbe| rgbe[c]));
+            write_rle(&channel, &mut out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Convert linear color to shared exponent r, g, b, e bytes
+fn to_rgbe(color: Color) -> [u8; 4] {
+    let (r, g, b) = (color.r.max(0.0), color.g.max(0.0), color.b.max(0.0));
+    let v = r.max(g).max(b);
+    if v < 1e-32 || !v.is_finite() {
+        return [0, 0, 0, 0];
+    }
+    // v = m * 2^e with m in [0.5, 1)
+    let mut e = v.log2().floor() as i32 + 1;
+    if v / 2f64.powi(e) >= 1.0 {
+        e += 1;
+    }
+    let scale = 256.0 / 2f64.powi(e);
+    [(r * scale) as u8, (g * scale) as u8, (b * scale) as u8, (e + 128).clamp(0, 255) as u8]
+}
+
+/// Run length encode one channel of a scanline
+///
+/// Runs of at least 3 equal bytes are written as (128 + count, byte), everything else is
+/// written as literal dumps of (count, bytes...).
+fn write_rle(data: &[u8], mut out: impl Write) -> Result<()> {
+    let mut i = 0;
+    while i < data.len() {
+        // find next run of at least 3 equal bytes
+        let mut run_start = i;
+        let mut run_len = 0;
+        while run_start < data.len() {
+            run_len = 1;
+            while run_start + run_len < data.len()
+                && run_len < MAX_RUN
+                && data[run_start + run_len] == data[run_start]
+            {
+                run_len += 1;
+            }
+            if run_len >= 3 {
+                break;
+            }
+            run_start += run_len;
+        }
+        // dump literal bytes before the run
+        while i < run_start {
+            let count = (run_start - i).min(MAX_RUN + 1);
+            out.write_all(&[count as u8])?;
+            out.write_all(&data[i..i + count])?;
+            i += count;
+        }
+        if run_len >= 3 && run_start < data.len() {
+            out.write_all(&[(128 + run_len) as u8, data[run_start]])?;
+            i = run_start + run_len;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_hdr_round_trip() {
+    let mut fb = Framebuffer::new(20, 3);
+    for i in 0..20 {
+        fb.set_pixel(i, 1, Color::new(15.0, 0.5 * (i as f64), 0.001), 1);
+    }
+    fb.set_pixel(3, 2, Color::new(1000.0, 2.0, 0.0), 1);
+    let mut hdr = Vec::new();
+    write_hdr(&fb, &mut hdr).unwrap();
+    let decoded = image::load_from_memory(&hdr).unwrap().to_rgb32f();
+    assert_eq!(decoded.dimensions(), (20, 3));
+    for (i, j, pixel) in decoded.enumerate_pixels() {
+        let expected = fb.pixel(i as usize, j as usize);
+        // shared exponent keeps 8 bits of precision relative to the brightest channel
+        let tolerance = expected.r.max(expected.g).max(expected.b) / 128.0 + 1e-6;
+        for (value, expected) in pixel.0.iter().zip([expected.r, expected.g, expected.b]) {
+            assert!(((*value as f64) - expected).abs() <= tolerance);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Probability density functions over directions used for importance sampling
+
+use crate::hittable::Hittable;
+use crate::vec3::{ Onb, Point3, Vec3 };
+use crate::rng::Pcg32;
+use std::f64::consts::PI;
+
+/// Distribution of directions that can be sampled and evaluated
+pub trait Pdf {
+    /// Solid angle probability density of generating direction
+    fn value(&self, direction: Vec3) -> f64;
+    /// Random direction drawn from the distribution
+    fn generate(&self, rng: &mut Pcg32) -> Vec3;
+}
+
+impl<P: Pdf + ?Sized> Pdf for &P {
+    fn value(&self, direction: Vec3) -> f64 {
+        (**self).value(direction)
+    }
+    fn generate(&self, rng: &mut Pcg32) -> Vec3 {
+        (**self).generate(rng)
+    }
+}
+
+/// Uniform distribution over all directions
+pub struct SpherePdf;
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+    fn generate(&self, rng: &mut Pcg32) -> Vec3 {
+        Vec3::random_unit_vector(rng)
+    }
+}
+
+/// Cosine weighted distribution over the hemisphere around a normal
+pub struct CosinePdf {
+    uvw: Onb,
+}
+impl CosinePdf {
+    pub fn new(normal: &Vec3) -> Self {
+        CosinePdf { uvw: Onb::new(normal) }
+    }
+}
+impl Pdf for CosinePdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        let cosine_theta = direction.unit_vector().dot(&self.uvw.w);
+        f64::max(0.0, cosine_theta / PI)
+    }
+    fn generate(&self, rng: &mut Pcg32) -> Vec3 {
+        self.uvw.transform(Vec3::random_cosine_direction(rng))
+    }
+}
+
+/// Phong lobe with density proportional to cos^exponent of the angle to an axis
+pub struct PhongPdf {
+    uvw: Onb,
+    exponent: f64,
+}
+impl PhongPdf {
+    pub fn new(axis: &Vec3, exponent: f64) -> Self {
+        PhongPdf { uvw: Onb::new(axis), exponent }
+    }
+}
+impl Pdf for PhongPdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        let cosine = direction.unit_vector().dot(&self.uvw.w);
+        if cosine <= 0.0 {
+            return 0.0;
+        }
+        (self.exponent + 1.0) / (2.0 * PI) * cosine.powf(self.exponent)
+    }
+    fn generate(&self, rng: &mut Pcg32) -> Vec3 {
+        let cos_theta = rng.next_f64().powf(1.0 / (self.exponent + 1.0));
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.next_f64();
+        self.uvw.transform(Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta))
+    }
+}
+
+/// Henyey-Greenstein phase function around the direction of travel. Anisotropy g in (-1, 1)
+/// is the mean cosine of the scattering angle: positive scatters forward, negative back.
+pub struct HenyeyGreensteinPdf {
+    uvw: Onb,
+    g: f64,
+}
+impl HenyeyGreensteinPdf {
+    pub fn new(direction: &Vec3, g: f64) -> Self {
+        HenyeyGreensteinPdf { uvw: Onb::new(direction), g }
+    }
+}
+impl Pdf for HenyeyGreensteinPdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        henyey_greenstein(direction.unit_vector().dot(&self.uvw.w), self.g)
+    }
+    fn generate(&self, rng: &mut Pcg32) -> Vec3 {
+        let g = self.g;
+        // invert the cdf of the scattering angle, uniform when nearly isotropic
+        let xi = rng.next_f64();
+        let cos_theta = match g.abs() < 1e-3 {
+            true => 1.0 - 2.0 * xi,
+            false => {
+                let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+                ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+            }
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.next_f64();
+        self.uvw.transform(Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta))
+    }
+}
+
+/// Henyey-Greenstein density for the cosine of the angle between the direction of travel and
+/// the scattered direction
+pub fn henyey_greenstein(cos_theta: f64, g: f64) -> f64 {
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+}
+
+/// Directions from origin toward points on a hittable, using its `pdf_value` and
+/// `random_direction`
+pub struct HittablePdf<'a> {
+    objects: &'a dyn Hittable,
+    origin: Point3,
+}
+impl<'a> HittablePdf<'a> {
+    pub fn new(objects: &'a dyn Hittable, origin: Point3) -> Self {
+        HittablePdf { objects, origin }
+    }
+}
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.objects.pdf_value(self.origin, direction)
+    }
+    fn generate(&self, rng: &mut Pcg32) -> Vec3 {
+        self.objects.random_direction(self.origin, rng)
+    }
+}
+
+/// Draws from `first` with probability `weight`, otherwise from `second`
+pub struct MixturePdf<A: Pdf, B: Pdf> {
+    weight: f64,
+    first: A,
+    second: B,
+}
+impl<A: Pdf, B: Pdf> MixturePdf<A, B> {
+    pub fn new(weight: f64, first: A, second: B) -> Self {
+        MixturePdf { weight, first, second }
+    }
+}
+impl<A: Pdf, B: Pdf> Pdf for MixturePdf<A, B> {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.weight * self.first.value(direction) +
+            (1.0 - self.weight) * self.second.value(direction)
+    }
+    fn generate(&self, rng: &mut Pcg32) -> Vec3 {
+        match rng.next_f64() < self.weight {
+            true => self.first.generate(rng),
+            false => self.second.generate(rng),
+        }
+    }
+}
+
+/// Multiple importance sampling weight for a sample drawn with density `pdf` when another
+/// strategy could have drawn it with density `other_pdf`, using Veach's power heuristic
+/// (beta = 2)
+pub fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b <= 0.0 {
+        return 0.0;
+    }
+    a / (a + b)
+}
+
+#[test]
+fn test_cosine_pdf() {
+    let normal = Vec3::new(0.0, 1.0, 1.0);
+    let pdf = CosinePdf::new(&normal);
+    let mut rng = Pcg32::new(0, 0);
+    for _ in 0..1000 {
+        let direction = pdf.generate(&mut rng);
+        assert!(direction.dot(&normal) >= 0.0);
+        assert!((direction.length() - 1.0).abs() < 1e-9);
+    }
+    assert!((pdf.value(normal) - 1.0 / PI).abs() < 1e-12);
+    assert_eq!(pdf.value(-normal), 0.0);
+}
+
+#[test]
+fn test_phong_and_mixture_normalised() {
+    let phong = PhongPdf::new(&Vec3::new(1.0, 0.0, 0.0), 20.0);
+    let mixture = MixturePdf::new(0.25, &phong, SpherePdf);
+    // integrate each density over the sphere of directions
+    let n = 400_000;
+    let (mut phong_sum, mut mixture_sum) = (0.0, 0.0);
+    let mut rng = Pcg32::new(0, 0);
+    for _ in 0..n {
+        let direction = Vec3::random_unit_vector(&mut rng);
+        phong_sum += phong.value(direction);
+        mixture_sum += mixture.value(direction);
+    }
+    let scale = 4.0 * PI / (n as f64);
+    assert!((phong_sum * scale - 1.0).abs() < 0.05);
+    assert!((mixture_sum * scale - 1.0).abs() < 0.05);
+    assert!(phong.generate(&mut rng).x > 0.0);
+}
+
+#[test]
+fn test_power_heuristic() {
+    assert_eq!(power_heuristic(1.0, 0.0), 1.0);
+    assert_eq!(power_heuristic(0.0, 0.0), 0.0);
+    assert!((power_heuristic(1.0, 2.0) + power_heuristic(2.0, 1.0) - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_henyey_greenstein() {
+    let forward = Vec3::new(0.0, 0.0, 1.0);
+    let mut rng = Pcg32::new(0, 0);
+    for g in [-0.7, 0.0, 0.3, 0.9] {
+        let pdf = HenyeyGreensteinPdf::new(&forward, g);
+        let n = 200_000;
+        // normalised over the sphere, and samples have mean cosine g
+        let integral: f64 = (0..n).map(|_| pdf.value(Vec3::random_unit_vector(&mut rng))).sum::<f64>() * 4.0 * PI / (n as f64);
+        let mean_cosine = (0..n).map(|_| pdf.generate(&mut rng).dot(&forward)).sum::<f64>() / (n as f64);
+        assert!((integral - 1.0).abs() < 0.05, "g {g}: {integral}");
+        assert!((mean_cosine - g).abs() < 0.01, "g {g}: {mean_cosine}");
+    }
+    assert!((henyey_greenstein(0.5, 0.0) - 1.0 / (4.0 * PI)).abs() < 1e-12);
+}
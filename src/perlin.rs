@@ -1,6 +1,6 @@
 //! Module to generate perlin noise
 
-use crate::random_int;
+use crate::rng::Pcg32;
 use crate::vec3::{ Point3, Vec3 };
 
 /// Perlin Permutation set of length N
@@ -12,13 +12,11 @@ impl<const N: usize> PerlinPerm<N> {
     fn new(perm: [i32; N]) -> Self {
         PerlinPerm { perm }
     }
-    pub fn generate() -> Self {
+    pub fn generate(rng: &mut Pcg32) -> Self {
         let mut arr: [i32; N] = std::array::from_fn(|i| i as i32);
         for i in (1..N).rev() {
-            let target = random_int(0, i as i32) as usize;
-            let tmp = arr[i];
-            arr[i] = arr[target];
-            arr[target] = tmp;
+            let target = rng.int(0, i as i32) as usize;
+            arr.swap(i, target);
         }
         PerlinPerm::new(arr)
     }
@@ -41,13 +39,22 @@ impl<const N: usize> PerlinGenerator<N> {
     ) -> Self {
         PerlinGenerator { rand_vecs, perm_x, perm_y, perm_z }
     }
+    /// Noise field for the default seed, the same on every run
     pub fn init() -> Self {
-        let rand_vecs: [Vec3; N] = std::array::from_fn(|_| { Vec3::random(-1.0, 1.0) });
-        let perm_x = PerlinPerm::<N>::generate();
-        let perm_y = PerlinPerm::<N>::generate();
-        let perm_z = PerlinPerm::<N>::generate();
+        PerlinGenerator::from_seed(0)
+    }
+    /// Noise field generated from seed
+    pub fn from_seed(seed: u64) -> Self {
+        let mut rng = Pcg32::new(seed, 0);
+        let rand_vecs: [Vec3; N] = std::array::from_fn(|_| {
+            Vec3::new(rng.float(-1.0, 1.0), rng.float(-1.0, 1.0), rng.float(-1.0, 1.0))
+        });
+        let perm_x = PerlinPerm::<N>::generate(&mut rng);
+        let perm_y = PerlinPerm::<N>::generate(&mut rng);
+        let perm_z = PerlinPerm::<N>::generate(&mut rng);
         PerlinGenerator::new(rand_vecs, perm_x, perm_y, perm_z)
     }
+    #[allow(clippy::needless_range_loop)]
     pub fn noise(&self, p: Point3) -> f64 {
         let u = p.x - p.x.floor();
         let v = p.y - p.y.floor();
@@ -85,6 +92,7 @@ impl<const N: usize> PerlinGenerator<N> {
 }
 
 /// Trilinear interpolation extrapolated to vectors instead of floats
+#[allow(clippy::needless_range_loop)]
 fn perlin_interp(c: [[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
     // hermitian smoothing
     let uu = u * u * (3.0 - 2.0 * u);
@@ -108,6 +116,7 @@ fn perlin_interp(c: [[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
     accum
 }
 
+#[allow(clippy::needless_range_loop)]
 fn _trilinear_interp(c: [[[f64; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
     let mut accum = 0.0;
     for i in 0..2 {
@@ -126,9 +135,10 @@ fn _trilinear_interp(c: [[[f64; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
 
 #[test]
 pub fn test_perm() {
-    let gen_perm = PerlinPerm::<10>::generate();
+    let gen_perm = PerlinPerm::<10>::generate(&mut Pcg32::new(1, 0));
     let default_perm = PerlinPerm::<10>::new(std::array::from_fn(|i| i as i32));
     assert_ne!(gen_perm, default_perm);
+    assert_eq!(gen_perm, PerlinPerm::<10>::generate(&mut Pcg32::new(1, 0)));
 }
 
 #[test]
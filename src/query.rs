@@ -0,0 +1,222 @@
+//! Ray queries against a built world, for collision and visibility checks outside rendering
+//!
+//! Queries see solid surfaces only, participating media are ignored.
+
+use crate::hittable::{ HitRecord, Hittable, ObjectId };
+use crate::hittable::bvh::Bvh;
+use crate::hittable::hittable_list::HittableList;
+use crate::ray::Ray;
+use crate::interval::Interval;
+use crate::vec3::{ Point3, Vec3 };
+use crate::aabb::Aabb;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+/// Distance past one hit the search for the next starts, relative to the hit's t, so a
+/// surface isn't found twice
+const HIT_EPSILON: f64 = 1e-10;
+/// Most crossings `all_hits` reports along one ray
+const MAX_HITS: usize = 4096;
+
+/// Where a ray met an object. Plain data, independent of materials.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueryHit {
+    pub object_id: ObjectId,
+    pub t: f64,
+    pub p: Point3,
+    pub normal: Vec3, // against the ray
+    pub front_face: bool, // ray arrived from outside
+    pub u: f64,
+    pub v: f64,
+}
+impl QueryHit {
+    fn from_record(rec: &HitRecord) -> Self {
+        QueryHit {
+            object_id: rec.object_id.expect("scene query objects are tagged"),
+            t: rec.t,
+            p: rec.p,
+            normal: rec.normal,
+            front_face: rec.front_face,
+            u: rec.u,
+            v: rec.v,
+        }
+    }
+}
+
+/// World prepared for ray queries. Each object is identified by its index in the list the
+/// query was built from.
+pub struct SceneQuery {
+    bvh: Bvh,
+    threads: usize, // used by batch queries
+}
+
+impl SceneQuery {
+    pub fn new(world: &HittableList<'static>) -> Self {
+        let mut objects: Vec<Arc<dyn Hittable>> = world.objects
+            .iter()
+            .enumerate()
+            .map(|(id, object)| Arc::new(Tagged { id, object: Arc::clone(object) }) as Arc<dyn Hittable>)
+            .collect();
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        SceneQuery { bvh: Bvh::from_list(&mut objects), threads }
+    }
+
+    /// Closest surface the ray meets within ray_t
+    pub fn closest_hit(&self, r: &Ray, ray_t: Interval) -> Option<QueryHit> {
+        self.bvh.hit(r, ray_t).map(|rec| QueryHit::from_record(&rec))
+    }
+
+    /// Every surface crossing within ray_t, sorted by t, up to the first `MAX_HITS`. Crossings
+    /// closer together than a small fraction of their distance are reported once.
+    pub fn all_hits(&self, r: &Ray, ray_t: Interval) -> Vec<QueryHit> {
+        let mut hits = Vec::new();
+        let mut t_min = ray_t.min;
+        while hits.len() < MAX_HITS {
+            let Some(hit) = self.closest_hit(r, Interval::new(t_min, ray_t.max)) else {
+                break;
+            };
+            // step at least one ulp so far hits can't be found again
+            t_min = (hit.t + HIT_EPSILON * hit.t.abs()).max(hit.t.next_up());
+            hits.push(hit);
+        }
+        hits
+    }
+
+    /// Whether any surface blocks the ray within ray_t
+    pub fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.bvh.occluded(r, ray_t)
+    }
+
+    /// Objects whose closed surface contains p, found by counting crossings of a ray from p.
+    /// Results are undefined for objects that aren't closed, e.g. a single quad.
+    pub fn inside(&self, p: Point3) -> Vec<ObjectId> {
+        // direction unlikely to run exactly along edges of axis aligned geometry
+        let r = Ray::new(p, Vec3::new(0.5773, 0.5774, 0.5775), 0.0);
+        let mut crossings: HashMap<ObjectId, usize> = HashMap::new();
+        for hit in self.all_hits(&r, Interval::new(0.0, f64::INFINITY)) {
+            *crossings.entry(hit.object_id).or_default() += 1;
+        }
+        let mut inside: Vec<ObjectId> = crossings
+            .into_iter()
+            .filter_map(|(id, count)| (count % 2 == 1).then_some(id))
+            .collect();
+        inside.sort_unstable();
+        inside
+    }
+
+    /// Whether object id's closed surface contains p
+    pub fn is_inside(&self, id: ObjectId, p: Point3) -> bool {
+        self.inside(p).contains(&id)
+    }
+
+    /// `closest_hit` for each ray, spread over threads
+    pub fn closest_hits(&self, rays: &[Ray], ray_t: Interval) -> Vec<Option<QueryHit>> {
+        self.batch(rays, |r| self.closest_hit(r, ray_t))
+    }
+
+    /// `occluded` for each ray, spread over threads
+    pub fn occluded_batch(&self, rays: &[Ray], ray_t: Interval) -> Vec<bool> {
+        self.batch(rays, |r| self.occluded(r, ray_t))
+    }
+
+    fn batch<T: Send>(&self, rays: &[Ray], query: impl Fn(&Ray) -> T + Sync) -> Vec<T> {
+        if rays.is_empty() {
+            return Vec::new();
+        }
+        let chunk_size = rays.len().div_ceil(self.threads);
+        thread::scope(|s| {
+            let handles: Vec<_> = rays
+                .chunks(chunk_size)
+                .map(|chunk| s.spawn(|| chunk.iter().map(&query).collect::<Vec<T>>()))
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("query thread panicked"))
+                .collect()
+        })
+    }
+}
+
+/// Object reporting its id in hit records
+struct Tagged {
+    id: ObjectId,
+    object: Arc<dyn Hittable>,
+}
+impl Hittable for Tagged {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let mut rec = self.object.hit(r, ray_t)?;
+        rec.object_id = Some(self.id);
+        Some(rec)
+    }
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.object.occluded(r, ray_t)
+    }
+    fn bounding_box(&self) -> &Aabb {
+        self.object.bounding_box()
+    }
+}
+
+#[test]
+fn test_scene_query() {
+    use crate::color::Color;
+    use crate::hittable::cube::Cube;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::lambertian::Lambertian;
+    use crate::texture::SolidColor;
+    let white = Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)));
+    let mut world = HittableList::new();
+    world.add(Sphere::new_static(Point3::new(0.0, 0.0, 0.0), 1.0, white)); // 0
+    world.add(Cube::from_points(Point3::new(3.0, -1.0, -1.0), Point3::new(5.0, 1.0, 1.0), white)); // 1
+    world.add(Sphere::new_static(Point3::new(4.0, 0.0, 0.0), 0.5, white)); // 2, inside the cube
+    let query = SceneQuery::new(&world);
+
+    let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+    let everything = Interval::new(0.0, f64::INFINITY);
+    let hit = query.closest_hit(&r, everything).unwrap();
+    assert_eq!((hit.object_id, hit.t), (0, 4.0));
+    assert!(hit.front_face);
+
+    let hits = query.all_hits(&r, everything);
+    let ids: Vec<ObjectId> = hits.iter().map(|hit| hit.object_id).collect();
+    assert_eq!(ids, vec![0, 0, 1, 2, 2, 1]);
+    assert!(hits.windows(2).all(|pair| pair[0].t < pair[1].t));
+
+    assert_eq!(query.inside(Point3::new(4.0, 0.1, 0.0)), vec![1, 2]);
+    assert_eq!(query.inside(Point3::new(3.2, 0.0, 0.0)), vec![1]);
+    assert!(query.is_inside(0, Point3::new(0.2, 0.3, -0.1)));
+    assert!(query.inside(Point3::new(2.0, 0.0, 0.0)).is_empty());
+
+    assert!(query.occluded(&r, Interval::new(0.0, 4.5)));
+    assert!(!query.occluded(&r, Interval::new(0.0, 3.5)));
+
+    let rays: Vec<Ray> = (0..100)
+        .map(|i| Ray::new(Point3::new(-5.0, (i as f64) * 0.03 - 1.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0))
+        .collect();
+    let batch = query.closest_hits(&rays, everything);
+    assert_eq!(batch.len(), rays.len());
+    for (r, hit) in rays.iter().zip(&batch) {
+        assert_eq!(*hit, query.closest_hit(r, everything));
+    }
+    let blocked = query.occluded_batch(&rays, Interval::new(0.0, 100.0));
+    assert_eq!(blocked, batch.iter().map(Option::is_some).collect::<Vec<bool>>());
+}
+
+#[test]
+fn test_all_hits_at_any_scale() {
+    use crate::color::Color;
+    use crate::hittable::cube::Cube;
+    use crate::material::lambertian::Lambertian;
+    use crate::texture::SolidColor;
+    let white = Lambertian::new(SolidColor::new(Color::new(1.0, 1.0, 1.0)));
+    let r = Ray::new(Point3::zero(), Vec3::new(1.0, 0.0, 0.0), 0.0);
+    let everything = Interval::new(0.0, f64::INFINITY);
+    for (near, size) in [(1e-8, 2e-8), (1.0, 2.0), (1e10, 2.0)] {
+        let mut world = HittableList::new();
+        let min = Point3::new(near, -size / 2.0, -size / 2.0);
+        world.add(Cube::from_points(min, min + Vec3::new(size, size, size), white));
+        let hits = SceneQuery::new(&world).all_hits(&r, everything);
+        assert_eq!(hits.len(), 2, "cube at {near}");
+        assert!((hits[1].t - hits[0].t - size).abs() < 1e-6 * size.max(1.0), "cube at {near}");
+    }
+}
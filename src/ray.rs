@@ -12,7 +12,7 @@ pub struct Ray {
 
 impl Ray {
     pub fn new(origin: Point3, direction: Vec3, time: f64) -> Self {
-        Ray { origin: origin, direction: direction, time }
+        Ray { origin, direction, time }
     }
     /// Point along ray at time t
     pub fn at(&self, t: f64) -> Point3 {
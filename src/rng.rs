@@ -0,0 +1,78 @@
+//! Seeded random number generation
+//!
+//! Generators are passed explicitly to everything that samples. The camera starts one for
+//! each pixel from the scene seed and the pixel's index, so a pixel's samples depend only on
+//! the seed and not on which thread rendered it or when.
+
+/// Multiplier of the PCG linear congruential step
+const PCG_MULTIPLIER: u64 = 6364136223846793005;
+
+/// PCG32 generator (XSH RR output on a 64 bit LCG) with selectable stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pcg32 {
+    state: u64,
+    increment: u64, // odd, selects one of 2^63 streams
+}
+
+impl Pcg32 {
+    /// Generator for the given seed and stream. Seeds and streams are scrambled first so
+    /// neighbouring values, like adjacent pixel indices, give unrelated sequences.
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Pcg32 { state: 0, increment: (splitmix64(stream) << 1) | 1 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(splitmix64(seed ^ splitmix64(stream)));
+        rng.next_u32();
+        rng
+    }
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(PCG_MULTIPLIER).wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+    /// Uniform float in [0, 1) with 53 bits of precision
+    pub fn next_f64(&mut self) -> f64 {
+        let bits = ((self.next_u32() as u64) << 21) ^ ((self.next_u32() as u64) >> 11);
+        (bits as f64) * (1.0 / ((1u64 << 53) as f64))
+    }
+    /// Uniform float in [min, max)
+    pub fn float(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+    /// Uniform integer in [min, max]
+    pub fn int(&mut self, min: i32, max: i32) -> i32 {
+        let span = ((max as i64) - (min as i64) + 1) as u64;
+        min + ((self.next_u32() as u64 * span) >> 32) as i32
+    }
+}
+
+/// Mix bits of x so nearby inputs give unrelated outputs
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[test]
+fn test_streams() {
+    let mut a = Pcg32::new(7, 1);
+    let mut b = Pcg32::new(7, 1);
+    let mut c = Pcg32::new(7, 2);
+    let first: Vec<u32> = (0..8).map(|_| a.next_u32()).collect();
+    assert_eq!(first, (0..8).map(|_| b.next_u32()).collect::<Vec<_>>());
+    assert_ne!(first, (0..8).map(|_| c.next_u32()).collect::<Vec<_>>());
+
+    let n = 100_000;
+    let mut counts = [0i32; 10];
+    let mut sum = 0.0;
+    for _ in 0..n {
+        let x = a.next_f64();
+        assert!((0.0..1.0).contains(&x));
+        sum += x;
+        counts[a.int(0, 9) as usize] += 1;
+    }
+    assert!((sum / (n as f64) - 0.5).abs() < 0.01);
+    assert!(counts.iter().all(|&count| (count - n / 10).abs() < n / 100));
+}
@@ -0,0 +1,556 @@
+//! Scene description files
+//!
+//! Scenes are TOML files with a camera, named textures and materials, and a list of objects:
+//!
+//! ```toml
+//! include = ["walls.toml"]          # read first, paths relative to this file
+//!
+//! [camera]                          # every key optional
+//! seed = 1                          # random seed, the same seed renders the same image
+//! image_width = 600
+//! lookfrom = [278, 278, -800]
+//! lookat = [278, 278, 0]
+//!
+//! [display]                         # optional, these are the defaults
+//! exposure = 0.0
+//! tonemap = "clamp"                 # clamp, reinhard, extended-reinhard[:white], hable or aces
+//! transfer = "gamma2"               # srgb, linear or gamma with an exponent, e.g. gamma2.2
+//!
+//! [textures.checker]
+//! type = "checker"                  # solid, checker, image or noise
+//! scale = 3.0
+//! even = [0.2, 0.3, 0.1]            # colors or names of other textures
+//! odd = [0.9, 0.9, 0.9]
+//!
+//! [materials.ground]
+//! type = "lambertian"               # lambertian, metal, dielectric, diffuse_light or isotropic
+//! albedo = "checker"                # color or texture name
+//!
+//! [[objects]]
+//! type = "box"                      # sphere, quad, box, triangle, mesh or constant_medium
+//! min = [0, 0, 0]
+//! max = [165, 330, 165]
+//! material = "ground"
+//! transform = [{ rotate = [0, 1, 0], degrees = 15 }, { translate = [265, 0, 295] }]
+//! ```
+//!
+//! Transforms apply in the order listed. Objects with a `diffuse_light` material are also
+//! sampled as lights unless they set `light = false`. Names defined later, including in the
+//! file that does the including, replace earlier ones. Errors name the file and key at fault,
+//! and unknown keys are errors so typos aren't silently ignored.
+
+use anyhow::{ Context, Result, anyhow, bail, ensure };
+use std::cell::RefCell;
+use std::collections::{ HashMap, HashSet };
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+use toml::{ Table, Value };
+use crate::camera::CameraArgs;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::hittable::Hittable;
+use crate::hittable::bvh::Bvh;
+use crate::hittable::constant_medium::ConstantMedium;
+use crate::hittable::cube::Cube;
+use crate::hittable::hittable_list::HittableList;
+use crate::hittable::instance::Instance;
+use crate::hittable::quad::Quad;
+use crate::hittable::sphere::Sphere;
+use crate::hittable::triangle::Triangle;
+use crate::image::Image;
+use crate::loader;
+use crate::material::Material;
+use crate::material::dialectric::Dialectric;
+use crate::material::diffuse_light::DiffuseLight;
+use crate::material::isotropic::Isotropic;
+use crate::material::lambertian::Lambertian;
+use crate::material::metal::Metal;
+use crate::texture::{ SolidColor, Texture };
+use crate::texture::checkered::CheckeredTexture;
+use crate::texture::image::ImageTexture;
+use crate::texture::noise::NoiseTexture;
+use crate::tonemap::{ DisplayPipeline, ToneMap, Transfer };
+use crate::vec3::{ Mat4, Point3, Vec3 };
+
+/// Objects, lights and camera read from a scene file
+pub struct Scene {
+    pub world: HittableList<'static>,
+    pub lights: HittableList<'static>, // emissive objects, also in world
+    pub camera: CameraArgs,
+    pub display: DisplayPipeline, // used when saving 8 bit images
+}
+
+impl Scene {
+    /// Render the scene through a BVH over its objects
+    pub fn render(mut self) -> Framebuffer {
+        let bvh = Bvh::from_list(&mut self.world.objects);
+        self.camera.initialize().render(bvh, &self.lights)
+    }
+}
+
+/// Load scene file and any files it includes
+pub fn load_scene(path: impl AsRef<Path>) -> Result<Scene> {
+    let mut definitions = Definitions::default();
+    definitions.read_file(path.as_ref(), &mut Vec::new())?;
+    definitions.build()
+}
+
+/// Parse the contents of a scene file. Includes and other files are found relative to
+/// base_dir.
+pub fn parse_scene(source: &str, base_dir: &Path) -> Result<Scene> {
+    let mut definitions = Definitions::default();
+    definitions.read_source(source, "scene", base_dir, &mut Vec::new())?;
+    definitions.build()
+}
+
+/// Table from a scene file, remembering its file and key path for error messages
+#[derive(Clone)]
+struct Entry {
+    table: Table,
+    path: String, // key path of the table, e.g. materials.red
+    file: String, // file the table came from
+    base_dir: PathBuf, // directory file paths in the table are relative to
+}
+
+/// Everything defined by a scene file and its includes, before objects are built
+#[derive(Default)]
+struct Definitions {
+    camera: Option<Entry>,
+    display: Option<Entry>,
+    textures: HashMap<String, Entry>,
+    materials: HashMap<String, Entry>,
+    objects: Vec<Entry>,
+}
+
+impl Definitions {
+    fn read_file(&mut self, path: &Path, including: &mut Vec<PathBuf>) -> Result<()> {
+        let source = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if including.contains(&canonical) {
+            bail!("{} includes itself", path.display());
+        }
+        including.push(canonical);
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        self.read_source(&source, &path.display().to_string(), base_dir, including)?;
+        including.pop();
+        Ok(())
+    }
+
+    fn read_source(&mut self, source: &str, file: &str, base_dir: &Path, including: &mut Vec<PathBuf>) -> Result<()> {
+        let table: Table = source.parse().with_context(|| format!("failed to parse {file}"))?;
+        let entry = |table: Table, path: String| Entry { table, path, file: file.to_string(), base_dir: base_dir.to_path_buf() };
+        let in_file = |key: &str| format!("{file}: {key}");
+
+        // includes first so this file's definitions replace theirs
+        if let Some(includes) = table.get("include") {
+            let includes = includes.as_array().ok_or_else(|| anyhow!("{}: expected an array of paths", in_file("include")))?;
+            for (i, include) in includes.iter().enumerate() {
+                let include = include.as_str().ok_or_else(|| anyhow!("{}: expected a path", in_file(&format!("include[{i}]"))))?;
+                self.read_file(&base_dir.join(include), including)
+                    .with_context(|| in_file(&format!("include[{i}]")))?;
+            }
+        }
+        for (key, value) in table {
+            match key.as_str() {
+                "include" => (),
+                "camera" => self.camera = Some(entry(as_table(value, &in_file("camera"))?, "camera".into())),
+                "display" => self.display = Some(entry(as_table(value, &in_file("display"))?, "display".into())),
+                "textures" | "materials" => {
+                    for (name, value) in as_table(value, &in_file(&key))? {
+                        let path = format!("{key}.{name}");
+                        let definition = entry(as_table(value, &in_file(&path))?, path);
+                        match key.as_str() {
+                            "textures" => self.textures.insert(name, definition),
+                            _ => self.materials.insert(name, definition),
+                        };
+                    }
+                }
+                "objects" => {
+                    let Value::Array(objects) = value else {
+                        bail!("{}: expected an array of tables, write [[objects]]", in_file("objects"));
+                    };
+                    for (i, object) in objects.into_iter().enumerate() {
+                        let path = format!("objects[{}]", self.objects.len());
+                        let object = as_table(object, &in_file(&format!("objects[{i}]")))?;
+                        self.objects.push(entry(object, path));
+                    }
+                }
+                _ => bail!("{}: unknown key", in_file(&key)),
+            }
+        }
+        Ok(())
+    }
+
+    fn build(self) -> Result<Scene> {
+        let camera = match &self.camera {
+            Some(entry) => build_camera(&Section::new(entry)).with_context(|| entry.file.clone())?,
+            None => bail!("no [camera] table in scene"),
+        };
+        let display = match &self.display {
+            Some(entry) => build_display(&Section::new(entry)).with_context(|| entry.file.clone())?,
+            None => DisplayPipeline::default(),
+        };
+        let objects = self.objects.clone();
+        let mut builder = Builder { definitions: self, textures: HashMap::new(), materials: HashMap::new(), resolving: HashSet::new() };
+        let mut world = HittableList::new();
+        let mut lights = HittableList::new();
+        for entry in &objects {
+            let (object, emissive) = builder.object(&Section::new(entry)).with_context(|| entry.file.clone())?;
+            if emissive {
+                lights.add(Arc::clone(&object));
+            }
+            world.add(object);
+        }
+        Ok(Scene { world, lights, camera, display })
+    }
+}
+
+fn build_camera(section: &Section) -> Result<CameraArgs> {
+    let mut camera = CameraArgs::new(
+        section.number_or("aspect_ratio", 16.0 / 9.0)?,
+        section.integer_or("image_width", 400)?,
+        section.integer_or("samples_per_pixel", 100)?,
+        section.integer_or("max_depth", 50)?,
+        section.color_or("background", Color::new(0.0, 0.0, 0.0))?,
+        section.number_or("vfov", 90.0)?,
+        section.vec3_or("lookfrom", Point3::new(0.0, 0.0, 0.0))?,
+        section.vec3_or("lookat", Point3::new(0.0, 0.0, -1.0))?,
+        section.vec3_or("vup", Vec3::new(0.0, 1.0, 0.0))?,
+        section.number_or("defocus_angle", 0.0)?,
+        section.number_or("focus_dist", 10.0)?
+    );
+    camera.russian_roulette_depth = section.integer_or("russian_roulette_depth", camera.russian_roulette_depth)?;
+    if let Some(seed) = section.get("seed") {
+        camera.seed = as_integer(seed, &section.key("seed"))? as u64;
+    }
+    if let Some(threads) = section.get("threads") {
+        camera.threads = as_integer(threads, &section.key("threads"))?.max(1) as usize;
+    }
+    section.finish()?;
+    Ok(camera)
+}
+
+/// Display pipeline with any keys left out taken from the default
+fn build_display(section: &Section) -> Result<DisplayPipeline> {
+    let mut display = DisplayPipeline::default();
+    display.exposure = section.number_or("exposure", display.exposure)?;
+    if section.get("tonemap").is_some() {
+        display.tone_map = ToneMap::from_name(section.string("tonemap")?).with_context(|| section.key("tonemap"))?;
+    }
+    if section.get("transfer").is_some() {
+        display.transfer = Transfer::from_name(section.string("transfer")?).with_context(|| section.key("transfer"))?;
+    }
+    section.finish()?;
+    Ok(display)
+}
+
+/// Builds textures and materials on first use, so definitions can refer to each other in
+/// any order
+struct Builder {
+    definitions: Definitions,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, (Arc<dyn Material>, bool)>, // and whether it emits
+    resolving: HashSet<String>, // definitions being built, to catch cycles
+}
+
+impl Builder {
+    fn texture(&mut self, name: &str, key: &str) -> Result<Arc<dyn Texture>> {
+        if let Some(texture) = self.textures.get(name) {
+            return Ok(Arc::clone(texture));
+        }
+        let entry = self.definitions.textures
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("{key}: no texture named {name}"))?;
+        self.begin(&entry)?;
+        let section = Section::new(&entry);
+        let texture: Arc<dyn Texture> = match section.string("type")? {
+            "solid" => Arc::new(SolidColor::new(section.color("color")?)),
+            "checker" => {
+                let scale = section.number("scale")?;
+                ensure!(scale > 0.0, "{}: must be positive", section.key("scale"));
+                let even = self.texture_value(&section, "even")?;
+                let odd = self.texture_value(&section, "odd")?;
+                Arc::new(CheckeredTexture::new(1.0 / scale, even, odd))
+            }
+            "image" => {
+                let file = entry.base_dir.join(section.string("file")?);
+                let image = Image::from_file(&file.to_string_lossy())
+                    .with_context(|| format!("{}: failed to load {}", section.key("file"), file.display()))?;
+                Arc::new(ImageTexture::new(image))
+            }
+            "noise" => Arc::new(NoiseTexture::new(section.number_or("scale", 1.0)?)),
+            other => bail!("{}: unknown texture type {other}", section.key("type")),
+        };
+        section.finish()?;
+        self.resolving.remove(&entry.path);
+        self.textures.insert(name.to_string(), Arc::clone(&texture));
+        Ok(texture)
+    }
+
+    /// Texture given by key as a color or the name of a texture
+    fn texture_value(&mut self, section: &Section, key: &str) -> Result<Arc<dyn Texture>> {
+        let full_key = section.key(key);
+        match section.required(key)? {
+            Value::String(name) => self.texture(name, &full_key),
+            value => Ok(Arc::new(SolidColor::new(Color::from(as_vec3(value, &full_key)?)))),
+        }
+    }
+
+    fn material(&mut self, name: &str, key: &str) -> Result<(Arc<dyn Material>, bool)> {
+        if let Some((material, emissive)) = self.materials.get(name) {
+            return Ok((Arc::clone(material), *emissive));
+        }
+        let entry = self.definitions.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("{key}: no material named {name}"))?;
+        self.begin(&entry)?;
+        let section = Section::new(&entry);
+        let kind = section.string("type")?;
+        let material: Arc<dyn Material> = match kind {
+            "lambertian" => Arc::new(Lambertian::new(self.texture_value(&section, "albedo")?)),
+            "metal" => Arc::new(Metal::new(section.color("albedo")?, section.number_or("fuzz", 0.0)?)),
+            "dielectric" => Arc::new(Dialectric::new(section.number_or("ior", 1.5)?)),
+            "diffuse_light" => Arc::new(DiffuseLight::new(self.texture_value(&section, "emit")?)),
+            "isotropic" => Arc::new(Isotropic::new(self.texture_value(&section, "albedo")?)),
+            other => bail!("{}: unknown material type {other}", section.key("type")),
+        };
+        section.finish()?;
+        let emissive = kind == "diffuse_light";
+        self.resolving.remove(&entry.path);
+        self.materials.insert(name.to_string(), (Arc::clone(&material), emissive));
+        Ok((material, emissive))
+    }
+
+    fn begin(&mut self, entry: &Entry) -> Result<()> {
+        ensure!(self.resolving.insert(entry.path.clone()), "{} refers to itself", entry.path);
+        Ok(())
+    }
+
+    /// Object described by section and whether it should be sampled as a light
+    fn object(&mut self, section: &Section) -> Result<(Arc<dyn Hittable>, bool)> {
+        let material = |builder: &mut Builder| {
+            builder.material(section.string("material")?, &section.key("material"))
+        };
+        let (object, emissive): (Arc<dyn Hittable>, bool) = match section.string("type")? {
+            "sphere" => {
+                let (material, emissive) = material(self)?;
+                let center = section.vec3("center")?;
+                let radius = section.number("radius")?;
+                let sphere = match section.get("center2") {
+                    Some(value) => Sphere::new_moving(center, as_vec3(value, &section.key("center2"))?, radius, material),
+                    None => Sphere::new_static(center, radius, material),
+                };
+                (Arc::new(sphere), emissive)
+            }
+            "quad" => {
+                let (material, emissive) = material(self)?;
+                let quad = Quad::new_static(section.vec3("q")?, section.vec3("u")?, section.vec3("v")?, material);
+                (Arc::new(quad), emissive)
+            }
+            "box" => {
+                let (material, emissive) = material(self)?;
+                (Arc::new(Cube::from_points(section.vec3("min")?, section.vec3("max")?, material)), emissive)
+            }
+            "triangle" => {
+                let (material, emissive) = material(self)?;
+                let triangle = Triangle::new(section.vec3("a")?, section.vec3("b")?, section.vec3("c")?, material);
+                (Arc::new(triangle), emissive)
+            }
+            "mesh" => {
+                let file = section.base_dir.join(section.string("file")?);
+                let mut model = loader::load(&file).with_context(|| section.key("file"))?;
+                let object: Arc<dyn Hittable> = Arc::new(Bvh::from_list(&mut model.objects));
+                // materials from the file unless one is given
+                match section.get("material") {
+                    Some(_) => {
+                        let (material, emissive) = material(self)?;
+                        (Arc::new(Instance::new(object, Mat4::identity()).with_material(material)), emissive)
+                    }
+                    None => (object, false),
+                }
+            }
+            "constant_medium" => {
+                let (boundary, _) = self.object(&section.table("boundary")?)?;
+                let density = section.number("density")?;
+                ensure!(density > 0.0, "{}: must be positive", section.key("density"));
+                let albedo = self.texture_value(section, "albedo")?;
+                (Arc::new(ConstantMedium::new(boundary, density, albedo)), false)
+            }
+            other => bail!("{}: unknown object type {other}", section.key("type")),
+        };
+        let object = match section.get("transform") {
+            Some(value) => Arc::new(Instance::new(object, transform(value, &section.key("transform"))?)),
+            None => object,
+        };
+        let light = match section.get("light") {
+            Some(value) => value.as_bool().ok_or_else(|| anyhow!("{}: expected true or false", section.key("light")))?,
+            None => emissive,
+        };
+        section.finish()?;
+        Ok((object, light))
+    }
+}
+
+/// Object to world matrix from a list of steps, each applied after the ones before it
+fn transform(value: &Value, key: &str) -> Result<Mat4> {
+    let steps = value.as_array().ok_or_else(|| anyhow!("{key}: expected an array of transforms"))?;
+    let mut matrix = Mat4::identity();
+    for (i, step) in steps.iter().enumerate() {
+        let key = format!("{key}[{i}]");
+        let table = step.as_table().ok_or_else(|| anyhow!("{key}: expected a table"))?;
+        let section = Section { table, path: key.clone(), base_dir: PathBuf::new(), used: RefCell::default() };
+        let step = if section.get("translate").is_some() {
+            Mat4::translation(section.vec3("translate")?)
+        } else if section.get("rotate").is_some() {
+            Mat4::rotation(&section.vec3("rotate")?, section.number("degrees")?)
+        } else if let Some(scale) = section.get("scale") {
+            let factors = match scale {
+                Value::Array(_) => as_vec3(scale, &section.key("scale"))?,
+                _ => {
+                    let s = as_number(scale, &section.key("scale"))?;
+                    Vec3::new(s, s, s)
+                }
+            };
+            ensure!(factors.x * factors.y * factors.z != 0.0, "{}: can't scale by 0", section.key("scale"));
+            Mat4::scale(factors)
+        } else {
+            bail!("{key}: expected translate, rotate or scale");
+        };
+        section.finish()?;
+        matrix = step * matrix;
+    }
+    Ok(matrix)
+}
+
+/// Table being read, tracking which keys were used so unknown ones can be reported
+struct Section<'a> {
+    table: &'a Table,
+    path: String,
+    base_dir: PathBuf,
+    used: RefCell<HashSet<String>>,
+}
+
+impl<'a> Section<'a> {
+    fn new(entry: &'a Entry) -> Self {
+        Section { table: &entry.table, path: entry.path.clone(), base_dir: entry.base_dir.clone(), used: RefCell::default() }
+    }
+    /// Full key path for error messages
+    fn key(&self, key: &str) -> String {
+        format!("{}.{key}", self.path)
+    }
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        self.used.borrow_mut().insert(key.to_string());
+        self.table.get(key)
+    }
+    fn required(&self, key: &str) -> Result<&'a Value> {
+        self.get(key).ok_or_else(|| anyhow!("{}: missing", self.key(key)))
+    }
+    fn number(&self, key: &str) -> Result<f64> {
+        as_number(self.required(key)?, &self.key(key))
+    }
+    fn number_or(&self, key: &str, default: f64) -> Result<f64> {
+        self.get(key).map_or(Ok(default), |value| as_number(value, &self.key(key)))
+    }
+    fn integer_or(&self, key: &str, default: i32) -> Result<i32> {
+        match self.get(key) {
+            Some(value) => {
+                let n = as_integer(value, &self.key(key))?;
+                i32::try_from(n).map_err(|_| anyhow!("{}: {n} is out of range", self.key(key)))
+            }
+            None => Ok(default),
+        }
+    }
+    fn string(&self, key: &str) -> Result<&'a str> {
+        self.required(key)?.as_str().ok_or_else(|| anyhow!("{}: expected a string", self.key(key)))
+    }
+    fn vec3(&self, key: &str) -> Result<Vec3> {
+        as_vec3(self.required(key)?, &self.key(key))
+    }
+    fn vec3_or(&self, key: &str, default: Vec3) -> Result<Vec3> {
+        self.get(key).map_or(Ok(default), |value| as_vec3(value, &self.key(key)))
+    }
+    fn color(&self, key: &str) -> Result<Color> {
+        self.vec3(key).map(Color::from)
+    }
+    fn color_or(&self, key: &str, default: Color) -> Result<Color> {
+        self.vec3_or(key, default.into()).map(Color::from)
+    }
+    fn table(&self, key: &str) -> Result<Section<'a>> {
+        let table = self.required(key)?.as_table().ok_or_else(|| anyhow!("{}: expected a table", self.key(key)))?;
+        Ok(Section { table, path: self.key(key), base_dir: self.base_dir.clone(), used: RefCell::default() })
+    }
+    /// Fail on keys that were never read, most likely misspelt
+    fn finish(&self) -> Result<()> {
+        let used = self.used.borrow();
+        match self.table.keys().find(|key| !used.contains(*key)) {
+            Some(key) => bail!("{}: unknown key", self.key(key)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn as_table(value: Value, key: &str) -> Result<Table> {
+    match value {
+        Value::Table(table) => Ok(table),
+        _ => bail!("{key}: expected a table"),
+    }
+}
+
+fn as_number(value: &Value, key: &str) -> Result<f64> {
+    match value {
+        Value::Float(x) => Ok(*x),
+        Value::Integer(n) => Ok(*n as f64),
+        _ => bail!("{key}: expected a number"),
+    }
+}
+
+fn as_integer(value: &Value, key: &str) -> Result<i64> {
+    value.as_integer().ok_or_else(|| anyhow!("{key}: expected an integer"))
+}
+
+fn as_vec3(value: &Value, key: &str) -> Result<Vec3> {
+    match value.as_array().map(Vec::as_slice) {
+        Some([x, y, z]) => Ok(Vec3::new(as_number(x, key)?, as_number(y, key)?, as_number(z, key)?)),
+        _ => bail!("{key}: expected an array of 3 numbers"),
+    }
+}
+
+#[test]
+fn test_load_cornell_box() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/cornell_box.toml");
+    let scene = load_scene(path).unwrap();
+    // walls and light from the included file, then the two boxes
+    assert_eq!(scene.world.objects.len(), 8);
+    assert_eq!(scene.lights.objects.len(), 1);
+    assert_eq!(scene.camera.image_width, 600);
+    assert_eq!(scene.camera.lookfrom, Point3::new(278.0, 278.0, -800.0));
+    assert_eq!(scene.camera.aspect_ratio, 1.0);
+    assert_eq!(scene.display, DisplayPipeline::new(0.0, ToneMap::AcesFitted, Transfer::Srgb));
+}
+
+#[test]
+fn test_scene_errors() {
+    let base = "[camera]\n[materials.white]\ntype = \"lambertian\"\nalbedo = [0.7, 0.7, 0.7]\n";
+    let error = |source: &str| format!("{:#}", parse_scene(&format!("{base}{source}"), Path::new("")).err().unwrap());
+
+    let scene = parse_scene(&format!("{base}[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, -1]\nradius = 0.5\nmaterial = \"white\"\n"), Path::new(""));
+    let scene = scene.unwrap();
+    assert_eq!(scene.world.objects.len(), 1);
+    // the same display as the built-in scenes when the file doesn't choose one
+    assert_eq!(scene.display, DisplayPipeline::default());
+
+    let err = error("[[objects]]\ntype = \"sphere\"\ncenter = [0, 0]\nradius = 0.5\nmaterial = \"white\"\n");
+    assert!(err.contains("objects[0].center: expected an array of 3 numbers"), "{err}");
+    let err = error("[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 1]\nradious = 0.5\nmaterial = \"white\"\n");
+    assert!(err.contains("objects[0].radius: missing"), "{err}");
+    let err = error("[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 1]\nradius = 0.5\nmaterial = \"red\"\n");
+    assert!(err.contains("objects[0].material: no material named red"), "{err}");
+    let err = error("[materials.loop]\ntype = \"lambertian\"\nalbedo = \"a\"\n[textures.a]\ntype = \"checker\"\nscale = 1\neven = \"a\"\nodd = [0, 0, 0]\n[[objects]]\ntype = \"quad\"\nq = [0, 0, 0]\nu = [1, 0, 0]\nv = [0, 1, 0]\nmaterial = \"loop\"\nlight = \"yes\"\n");
+    assert!(err.contains("textures.a refers to itself"), "{err}");
+    let err = error("[[objects]]\ntype = \"box\"\nmin = [0, 0, 0]\nmax = [1, 1, 1]\nmaterial = \"white\"\ntransform = [{ rotate = [0, 1, 0] }]\n");
+    assert!(err.contains("objects[0].transform[0].degrees: missing"), "{err}");
+    let err = error("[display]\ntonemap = \"extended-reinhard:-1\"\n");
+    assert!(err.contains("display.tonemap: extended-reinhard white point"), "{err}");
+}
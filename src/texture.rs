@@ -2,13 +2,29 @@
 
 use crate::vec3::Point3;
 use crate::color::Color;
+use crate::hittable::HitRecord;
+use std::sync::Arc;
 
 pub mod checkered;
 pub mod image;
 pub mod noise;
+pub mod vertex_color;
 
-pub trait Texture {
+pub trait Texture: Send + Sync {
     fn color(&self, u: f64, v: f64, p: Point3) -> Color;
+    /// Color at a hit. Textures that need more of the hit than u, v and p override this.
+    fn value(&self, rec: &HitRecord) -> Color {
+        self.color(rec.u, rec.v, rec.p)
+    }
+}
+
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn color(&self, u: f64, v: f64, p: Point3) -> Color {
+        (**self).color(u, v, p)
+    }
+    fn value(&self, rec: &HitRecord) -> Color {
+        (**self).value(rec)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -3,27 +3,27 @@
 use crate::texture::{ Texture, SolidColor };
 use crate::color::Color;
 use crate::vec3::Point3;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct CheckeredTexture {
     inv_scale: f64,
-    even_texture: Rc<dyn Texture>,
-    odd_texture: Rc<dyn Texture>,
+    even_texture: Arc<dyn Texture>,
+    odd_texture: Arc<dyn Texture>,
 }
 impl CheckeredTexture {
     pub fn new(
         inv_scale: f64,
-        even_texture: Rc<dyn Texture>,
-        odd_texture: Rc<dyn Texture>
+        even_texture: Arc<dyn Texture>,
+        odd_texture: Arc<dyn Texture>
     ) -> Self {
         CheckeredTexture { inv_scale, even_texture, odd_texture }
     }
     pub fn from_solids(inv_scale: f64, even_color: Color, odd_color: Color) -> Self {
         CheckeredTexture::new(
             inv_scale,
-            Rc::new(SolidColor::new(even_color)),
-            Rc::new(SolidColor::new(odd_color))
+            Arc::new(SolidColor::new(even_color)),
+            Arc::new(SolidColor::new(odd_color))
         )
     }
 }
@@ -34,9 +34,9 @@ impl Texture for CheckeredTexture {
         let y = (self.inv_scale * p.y).floor() as i32;
         let z = (self.inv_scale * p.z).floor() as i32;
         if (x + y + z) % 2 == 0 {
-            return self.even_texture.color(u, v, p);
+            self.even_texture.color(u, v, p)
         } else {
-            return self.odd_texture.color(u, v, p);
+            self.odd_texture.color(u, v, p)
         }
     }
 }
@@ -18,7 +18,7 @@ impl ImageTexture {
 
 impl Texture for ImageTexture {
     fn color(&self, u: f64, v: f64, _p: Point3) -> Color {
-        if self.image.height <= 0 { return Color::new(0.0, 1.0, 1.0) }
+        if self.image.height == 0 { return Color::new(0.0, 1.0, 1.0) }
         
         let u = Interval::new(0.0, 1.0).itv_clamp(u);
         let v = 1.0 - Interval::new(0.0, 1.0).itv_clamp(v);
@@ -0,0 +1,27 @@
+//! Texture using colors stored on mesh vertices
+
+use crate::texture::Texture;
+use crate::color::Color;
+use crate::vec3::Point3;
+use crate::hittable::HitRecord;
+
+/// Color interpolated from the vertices of the mesh hit, or `fallback` for objects without
+/// vertex colors
+#[derive(Debug, Clone, Copy)]
+pub struct VertexColorTexture {
+    fallback: Color,
+}
+impl VertexColorTexture {
+    pub fn new(fallback: Color) -> Self {
+        VertexColorTexture { fallback }
+    }
+}
+
+impl Texture for VertexColorTexture {
+    fn color(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        self.fallback
+    }
+    fn value(&self, rec: &HitRecord) -> Color {
+        rec.vertex_color.unwrap_or(self.fallback)
+    }
+}
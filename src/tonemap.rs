@@ -0,0 +1,243 @@
+//! Display pipeline turning linear radiance into 8 bit display values
+//!
+//! A pixel is scaled by the exposure, compressed into [0, 1] by a tone mapping operator,
+//! then encoded with a transfer function. The default pipeline reproduces the original
+//! clamp and square root gamma of `color::write_color`.
+
+use anyhow::{ Result, bail };
+use crate::color::Color;
+use crate::interval::Interval;
+
+/// Operator mapping unbounded linear values into [0, 1]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    Clamp, // Hard clip at 1
+    Reinhard, // L / (1 + L) on luminance
+    ExtendedReinhard { white: f64 }, // Reinhard where luminance `white` maps to 1
+    Hable, // Uncharted 2 filmic curve
+    AcesFitted, // Stephen Hill's fit of the ACES reference and output transforms
+}
+
+/// White point of `extended-reinhard` when the name gives none
+const DEFAULT_REINHARD_WHITE: f64 = 4.0;
+
+/// Encoding from linear [0, 1] values to display values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transfer {
+    Linear,
+    Gamma(f64), // x^(1 / gamma)
+    Srgb, // Piecewise sRGB curve
+}
+
+/// Exposure, tone mapping and transfer function applied to each pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayPipeline {
+    pub exposure: f64, // Exposure adjustment in stops
+    pub tone_map: ToneMap,
+    pub transfer: Transfer,
+}
+
+impl DisplayPipeline {
+    pub fn new(exposure: f64, tone_map: ToneMap, transfer: Transfer) -> Self {
+        DisplayPipeline { exposure, tone_map, transfer }
+    }
+    /// Map linear color to display color with components in [0, 1]
+    pub fn apply(&self, pixel_color: Color) -> Color {
+        let exposed = 2f64.powf(self.exposure) * pixel_color;
+        let mapped = self.tone_map.apply(exposed);
+        let unit = Interval::new(0.0, 1.0);
+        Color::new(
+            self.transfer.encode(unit.itv_clamp(mapped.r)),
+            self.transfer.encode(unit.itv_clamp(mapped.g)),
+            self.transfer.encode(unit.itv_clamp(mapped.b))
+        )
+    }
+    /// Map linear color to 8 bit r, g, b components
+    pub fn to_bytes(&self, pixel_color: Color) -> [u8; 3] {
+        let c = self.apply(pixel_color);
+
+        // Translate [0, 1] components to [0, 255]
+        let intensity = Interval::new(0.0, 0.999);
+        let rbyte = (256.0 * intensity.itv_clamp(c.r)) as u8;
+        let gbyte = (256.0 * intensity.itv_clamp(c.g)) as u8;
+        let bbyte = (256.0 * intensity.itv_clamp(c.b)) as u8;
+        [rbyte, gbyte, bbyte]
+    }
+}
+
+impl Default for DisplayPipeline {
+    /// No exposure change, hard clamp and gamma 2
+    fn default() -> Self {
+        DisplayPipeline::new(0.0, ToneMap::Clamp, Transfer::Gamma(2.0))
+    }
+}
+
+impl ToneMap {
+    /// Operator by name: clamp, reinhard, extended-reinhard[:white], hable or aces
+    pub fn from_name(name: &str) -> Result<Self> {
+        let (name, white) = match name.split_once(':') {
+            Some((name, white)) => (name, Some(white)),
+            None => (name, None),
+        };
+        match (name, white) {
+            ("clamp", None) => Ok(ToneMap::Clamp),
+            ("reinhard", None) => Ok(ToneMap::Reinhard),
+            ("extended-reinhard", None) => Ok(ToneMap::ExtendedReinhard { white: DEFAULT_REINHARD_WHITE }),
+            ("extended-reinhard", Some(white)) => match white.parse::<f64>() {
+                Ok(white) if white > 0.0 => Ok(ToneMap::ExtendedReinhard { white }),
+                _ => bail!("extended-reinhard white point must be a positive number, got {white}"),
+            },
+            ("hable", None) => Ok(ToneMap::Hable),
+            ("aces", None) => Ok(ToneMap::AcesFitted),
+            _ => bail!("unknown tone map {name}, expected clamp, reinhard, extended-reinhard[:white], hable or aces"),
+        }
+    }
+    pub fn apply(&self, c: Color) -> Color {
+        match self {
+            ToneMap::Clamp => c,
+            ToneMap::Reinhard => scale_luminance(c, |l| l / (1.0 + l)),
+            ToneMap::ExtendedReinhard { white } => {
+                let white_sq = white * white;
+                scale_luminance(c, |l| (l * (1.0 + l / white_sq)) / (1.0 + l))
+            }
+            ToneMap::Hable => {
+                // exposure bias and linear white point of the original curve
+                let white_scale = 1.0 / hable_partial(11.2);
+                Color::new(
+                    hable_partial(2.0 * c.r) * white_scale,
+                    hable_partial(2.0 * c.g) * white_scale,
+                    hable_partial(2.0 * c.b) * white_scale
+                )
+            }
+            ToneMap::AcesFitted => aces_fitted(c),
+        }
+    }
+}
+
+impl Transfer {
+    /// Transfer function by name: srgb, linear or gamma followed by the exponent, e.g. gamma2.2
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "srgb" => Ok(Transfer::Srgb),
+            "linear" => Ok(Transfer::Linear),
+            _ => match name.strip_prefix("gamma").map(str::parse::<f64>) {
+                Some(Ok(gamma)) if gamma > 0.0 => Ok(Transfer::Gamma(gamma)),
+                _ => bail!("unknown transfer function {name}, expected srgb, gamma2.2 (or another exponent) or linear"),
+            },
+        }
+    }
+    /// Encode linear value in [0, 1]
+    pub fn encode(&self, linear_component: f64) -> f64 {
+        if linear_component <= 0.0 {
+            return 0.0;
+        }
+        match self {
+            Transfer::Linear => linear_component,
+            Transfer::Gamma(gamma) => linear_component.powf(1.0 / gamma),
+            Transfer::Srgb => {
+                if linear_component <= 0.0031308 {
+                    12.92 * linear_component
+                } else {
+                    1.055 * linear_component.powf(1.0 / 2.4) - 0.055
+                }
+            }
+        }
+    }
+}
+
+/// Relative luminance of linear sRGB color
+fn luminance(c: Color) -> f64 {
+    0.2126 * c.r + 0.7152 * c.g + 0.0722 * c.b
+}
+
+/// Tone map luminance with curve f and scale color to match, preserving hue
+fn scale_luminance(c: Color, f: impl Fn(f64) -> f64) -> Color {
+    let l = luminance(c);
+    if l <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    (f(l) / l) * c
+}
+
+/// John Hable's filmic curve without white point normalisation
+fn hable_partial(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.5, 0.1, 0.2, 0.02, 0.3);
+    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
+
+/// ACES curve fitted by Stephen Hill, including the sRGB to ACES input and output matrices
+fn aces_fitted(c: Color) -> Color {
+    let input = [
+        [0.59719, 0.35458, 0.04823],
+        [0.076, 0.90834, 0.01566],
+        [0.0284, 0.13383, 0.83777],
+    ];
+    let output = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+    let mul = |m: [[f64; 3]; 3], c: [f64; 3]| {
+        m.map(|row| row[0] * c[0] + row[1] * c[1] + row[2] * c[2])
+    };
+    let rrt_and_odt_fit = |v: f64| {
+        (v * (v + 0.0245786) - 0.000090537) / (v * (0.983729 * v + 0.432951) + 0.238081)
+    };
+    let [r, g, b] = mul(output, mul(input, [c.r, c.g, c.b]).map(rrt_and_odt_fit));
+    Color::new(r, g, b)
+}
+
+#[test]
+fn test_default_matches_sqrt_gamma() {
+    let pipeline = DisplayPipeline::default();
+    assert_eq!(pipeline.to_bytes(Color::new(0.25, 4.0, -1.0)), [128, 255, 0]);
+    assert_eq!(pipeline.to_bytes(Color::new(1.0, 0.0, 0.0)), [255, 0, 0]);
+}
+
+#[test]
+fn test_tone_maps_roll_off() {
+    let operators = [
+        ToneMap::Reinhard,
+        ToneMap::ExtendedReinhard { white: 256.0 },
+        ToneMap::Hable,
+        ToneMap::AcesFitted,
+    ];
+    for tone_map in operators {
+        let mut last = 0.0;
+        for stop in -8..3 {
+            let x = 2f64.powi(stop);
+            let y = tone_map.apply(Color::new(x, x, x)).g;
+            assert!(y > last, "{tone_map:?} not increasing at {x}");
+            assert!(y <= 1.0 + 1e-3, "{tone_map:?} above 1 at {x}");
+            last = y;
+        }
+    }
+    let white = ToneMap::ExtendedReinhard { white: 15.0 }.apply(Color::new(15.0, 15.0, 15.0));
+    assert!((white.r - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_transfer() {
+    assert!((Transfer::Srgb.encode(0.5) - 0.735357).abs() < 1e-6);
+    assert!((Transfer::Srgb.encode(0.002) - 0.02584).abs() < 1e-6);
+    assert!((Transfer::Gamma(2.2).encode(0.5) - 0.5f64.powf(1.0 / 2.2)).abs() < 1e-12);
+    assert_eq!(Transfer::Linear.encode(-1.0), 0.0);
+}
+
+#[test]
+fn test_exposure() {
+    let pipeline = DisplayPipeline::new(1.0, ToneMap::Clamp, Transfer::Linear);
+    assert_eq!(pipeline.apply(Color::new(0.25, 0.5, 1.0)), Color::new(0.5, 1.0, 1.0));
+}
+
+#[test]
+fn test_from_name() {
+    assert_eq!(ToneMap::from_name("aces").unwrap(), ToneMap::AcesFitted);
+    assert_eq!(ToneMap::from_name("extended-reinhard:2.5").unwrap(), ToneMap::ExtendedReinhard { white: 2.5 });
+    assert_eq!(ToneMap::from_name("extended-reinhard").unwrap(), ToneMap::ExtendedReinhard { white: DEFAULT_REINHARD_WHITE });
+    assert!(ToneMap::from_name("extended-reinhard:0").is_err());
+    assert!(ToneMap::from_name("aces:2").is_err());
+    assert_eq!(Transfer::from_name("gamma2.2").unwrap(), Transfer::Gamma(2.2));
+    assert_eq!(Transfer::from_name("srgb").unwrap(), Transfer::Srgb);
+    assert!(Transfer::from_name("gamma").is_err());
+}
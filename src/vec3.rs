@@ -1,7 +1,7 @@
 //! Define 3D Vector
 
 use std::ops;
-use crate::random_float;
+use crate::rng::Pcg32;
 
 /// Generic 3 dimensional vector
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -13,7 +13,7 @@ pub struct Vec3 {
 
 impl Vec3 {
     pub fn new(x: f64, y: f64, z: f64) -> Self {
-        Vec3 { x: x, y: y, z: z }
+        Vec3 { x, y, z }
     }
     pub fn zero() -> Self {
         Vec3::new(0.0, 0.0, 0.0)
@@ -26,8 +26,8 @@ impl Vec3 {
         }
     }
     /// Generate vector given random components with values from min to max
-    pub fn random(min: f64, max: f64) -> Self {
-        Vec3::new(random_float(min, max), random_float(min, max), random_float(min, max))
+    pub fn random(rng: &mut Pcg32, min: f64, max: f64) -> Self {
+        Vec3::new(rng.float(min, max), rng.float(min, max), rng.float(min, max))
     }
 
     /// Equivalent to dot product of vector with itself
@@ -73,9 +73,9 @@ impl Vec3 {
     }
 
     /// Generate random unit vector
-    pub fn random_unit_vector() -> Self {
+    pub fn random_unit_vector(rng: &mut Pcg32) -> Self {
         loop {
-            let p = Vec3::random(-1.0, 1.0);
+            let p = Vec3::random(rng, -1.0, 1.0);
             let lensq = p.length_squared();
             if 1e-160 < lensq && lensq <= 1.0 {
                 return p / lensq.sqrt();
@@ -83,18 +83,29 @@ impl Vec3 {
         }
     }
     /// Generate random unit vector on same hemisphere as normal vector
-    pub fn random_on_hemisphere(normal: &Vec3) -> Self {
-        let ruv = Self::random_unit_vector();
+    pub fn random_on_hemisphere(rng: &mut Pcg32, normal: &Vec3) -> Self {
+        let ruv = Self::random_unit_vector(rng);
         if ruv.dot(normal) > 0.0 {
-            return ruv;
+            ruv
         } else {
-            return -ruv;
+            -ruv
         }
     }
+    /// Generate random unit vector around z with density proportional to its z component
+    pub fn random_cosine_direction(rng: &mut Pcg32) -> Self {
+        let r1 = rng.next_f64();
+        let r2 = rng.next_f64();
+
+        let phi = 2.0 * std::f64::consts::PI * r1;
+        let x = phi.cos() * r2.sqrt();
+        let y = phi.sin() * r2.sqrt();
+        let z = (1.0 - r2).sqrt();
+        Vec3::new(x, y, z)
+    }
     /// Generate random vector in unit disk
-    pub fn random_in_unit_disk() -> Self {
+    pub fn random_in_unit_disk(rng: &mut Pcg32) -> Self {
         loop {
-            let p = Vec3::new(random_float(-1.0, 1.0), random_float(-1.0, 1.0), 0.0);
+            let p = Vec3::new(rng.float(-1.0, 1.0), rng.float(-1.0, 1.0), 0.0);
             if p.length_squared() < 1.0 {
                 return p;
             }
@@ -105,36 +116,384 @@ impl Vec3 {
 /// Wrapper specifying point in space rather than vector
 pub type Point3 = Vec3;
 
+/// Orthonormal basis with w aligned to a given direction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+impl Onb {
+    /// Build basis around normal n
+    pub fn new(n: &Vec3) -> Self {
+        let w = n.unit_vector();
+        // pick any axis not parallel to w
+        let a = match w.x.abs() > 0.9 {
+            true => Vec3::new(0.0, 1.0, 0.0),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let v = w.cross(&a).unit_vector();
+        // right handed, u x v = w
+        let u = v.cross(&w);
+        Onb { u, v, w }
+    }
+    /// Transform vector from basis coordinates to world coordinates
+    pub fn transform(&self, v: Vec3) -> Vec3 {
+        v.x * self.u + v.y * self.v + v.z * self.w
+    }
+    /// Transform vector from world coordinates to basis coordinates
+    pub fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::new(v.dot(&self.u), v.dot(&self.v), v.dot(&self.w))
+    }
+}
+
+/// 3x3 matrix, stored row by row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat3 {
+    pub m: [[f64; 3]; 3],
+}
+impl Mat3 {
+    pub fn new(m: [[f64; 3]; 3]) -> Self {
+        Mat3 { m }
+    }
+    pub fn identity() -> Self {
+        Mat3::new([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+    }
+    pub fn from_columns(c0: Vec3, c1: Vec3, c2: Vec3) -> Self {
+        Mat3::new([[c0.x, c1.x, c2.x], [c0.y, c1.y, c2.y], [c0.z, c1.z, c2.z]])
+    }
+    pub fn row(&self, i: usize) -> Vec3 {
+        Vec3::new(self.m[i][0], self.m[i][1], self.m[i][2])
+    }
+    pub fn column(&self, j: usize) -> Vec3 {
+        Vec3::new(self.m[0][j], self.m[1][j], self.m[2][j])
+    }
+    pub fn transpose(&self) -> Self {
+        Mat3::from_columns(self.row(0), self.row(1), self.row(2))
+    }
+    pub fn determinant(&self) -> f64 {
+        self.row(0).dot(&self.row(1).cross(&self.row(2)))
+    }
+    /// Inverse from the adjugate, None when the matrix is singular
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let (r0, r1, r2) = (self.row(0), self.row(1), self.row(2));
+        Some((1.0 / det) * Mat3::from_columns(r1.cross(&r2), r2.cross(&r0), r0.cross(&r1)))
+    }
+}
+
+/// 4x4 matrix acting on homogeneous coordinates, stored row by row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+impl Mat4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Mat4 { m }
+    }
+    pub fn identity() -> Self {
+        Mat4::scale(Vec3::new(1.0, 1.0, 1.0))
+    }
+    /// Affine transform applying linear then translating by offset
+    pub fn from_linear(linear: Mat3, offset: Vec3) -> Self {
+        let row = |i: usize| {
+            let r = linear.row(i);
+            [r.x, r.y, r.z, offset[i]]
+        };
+        Mat4::new([row(0), row(1), row(2), [0.0, 0.0, 0.0, 1.0]])
+    }
+    pub fn translation(offset: Vec3) -> Self {
+        Mat4::from_linear(Mat3::identity(), offset)
+    }
+    /// Counter-clockwise rotation by angle degrees looking down axis toward the origin
+    pub fn rotation(axis: &Vec3, degrees: f64) -> Self {
+        Mat4::from_quaternion(&Quaternion::from_axis_angle(axis, degrees))
+    }
+    /// Rotation by a unit quaternion
+    pub fn from_quaternion(q: &Quaternion) -> Self {
+        Mat4::from_linear(q.to_mat3(), Vec3::zero())
+    }
+    /// Scale by a separate factor along each axis
+    pub fn scale(factors: Vec3) -> Self {
+        let linear = Mat3::new([[factors.x, 0.0, 0.0], [0.0, factors.y, 0.0], [0.0, 0.0, factors.z]]);
+        Mat4::from_linear(linear, Vec3::zero())
+    }
+    /// Upper left 3x3 block, the transform without translation
+    pub fn linear(&self) -> Mat3 {
+        let m = &self.m;
+        Mat3::new([
+            [m[0][0], m[0][1], m[0][2]],
+            [m[1][0], m[1][1], m[1][2]],
+            [m[2][0], m[2][1], m[2][2]],
+        ])
+    }
+    pub fn transpose(&self) -> Self {
+        let mut t = [[0.0; 4]; 4];
+        for (i, row) in self.m.iter().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                t[j][i] = *value;
+            }
+        }
+        Mat4::new(t)
+    }
+    /// Inverse by Gauss-Jordan elimination, None when the matrix is singular
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Mat4::identity().m;
+        for col in 0..4 {
+            // partial pivoting on the largest remaining entry of the column
+            let pivot = (col..4).max_by(|i, j| a[*i][col].abs().total_cmp(&a[*j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+            let scale = 1.0 / a[col][col];
+            for k in 0..4 {
+                a[col][k] *= scale;
+                inv[col][k] *= scale;
+            }
+            for row in (0..4).filter(|r| *r != col) {
+                let factor = a[row][col];
+                for k in 0..4 {
+                    a[row][k] -= factor * a[col][k];
+                    inv[row][k] -= factor * inv[col][k];
+                }
+            }
+        }
+        Some(Mat4::new(inv))
+    }
+    /// Transform point, applying translation
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        self.transform_vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+    /// Transform direction, ignoring translation
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        self.linear() * v
+    }
+}
+
+/// Quaternion w + xi + yj + zk. Unit quaternions represent rotations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+impl Quaternion {
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Quaternion { w, x, y, z }
+    }
+    pub fn identity() -> Self {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+    /// Counter-clockwise rotation by angle degrees looking down axis toward the origin
+    pub fn from_axis_angle(axis: &Vec3, degrees: f64) -> Self {
+        let (sin, cos) = (0.5 * degrees.to_radians()).sin_cos();
+        let a = sin * axis.unit_vector();
+        Quaternion::new(cos, a.x, a.y, a.z)
+    }
+    /// Vector part x, y, z
+    pub fn vector(&self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+    pub fn conjugate(&self) -> Self {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+    pub fn dot(&self, rhs: &Quaternion) -> f64 {
+        self.w * rhs.w + self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+    pub fn length(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+    pub fn unit_quaternion(&self) -> Self {
+        (1.0 / self.length()) * *self
+    }
+    /// Rotate vector v by this unit quaternion
+    pub fn rotate(&self, v: Vec3) -> Vec3 {
+        let u = self.vector();
+        let t = 2.0 * u.cross(&v);
+        v + self.w * t + u.cross(&t)
+    }
+    /// Rotation matrix of this unit quaternion
+    pub fn to_mat3(&self) -> Mat3 {
+        let (w, x, y, z) = (self.w, self.x, self.y, self.z);
+        Mat3::new([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)],
+        ])
+    }
+    /// Spherical linear interpolation between unit quaternions, taking the shorter arc
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Self {
+        let mut cos_theta = self.dot(other);
+        let mut other = *other;
+        // q and -q are the same rotation
+        if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            other = -1.0 * other;
+        }
+        // nearly parallel, fall back to normalised linear interpolation
+        if cos_theta > 0.9995 {
+            return ((1.0 - t) * *self + t * other).unit_quaternion();
+        }
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin_theta;
+        let b = (t * theta).sin() / sin_theta;
+        a * *self + b * other
+    }
+}
+
 impl ops::Add<Vec3> for Vec3 {
     type Output = Vec3;
     fn add(self, rhs: Vec3) -> Vec3 {
         Vec3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
     }
 }
+impl ops::AddAssign<Vec3> for Vec3 {
+    fn add_assign(&mut self, rhs: Vec3) {
+        *self = *self + rhs;
+    }
+}
 impl ops::Sub<Vec3> for Vec3 {
     type Output = Vec3;
     fn sub(self, rhs: Vec3) -> Vec3 {
         Vec3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
     }
 }
+impl ops::SubAssign<Vec3> for Vec3 {
+    fn sub_assign(&mut self, rhs: Vec3) {
+        *self = *self - rhs;
+    }
+}
 impl ops::Mul<Vec3> for f64 {
     type Output = Vec3;
     fn mul(self, rhs: Vec3) -> Vec3 {
         Vec3::new(rhs.x * self, rhs.y * self, rhs.z * self)
     }
 }
+impl ops::Mul<f64> for Vec3 {
+    type Output = Vec3;
+    fn mul(self, rhs: f64) -> Vec3 {
+        rhs * self
+    }
+}
+/// Component-wise product
+impl ops::Mul<Vec3> for Vec3 {
+    type Output = Vec3;
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        Vec3::new(self.x * rhs.x, self.y * rhs.y, self.z * rhs.z)
+    }
+}
+impl ops::MulAssign<f64> for Vec3 {
+    fn mul_assign(&mut self, rhs: f64) {
+        *self = rhs * *self;
+    }
+}
 impl ops::Div<f64> for Vec3 {
     type Output = Vec3;
     fn div(self, rhs: f64) -> Vec3 {
         Vec3::new(self.x / rhs, self.y / rhs, self.z / rhs)
     }
 }
+impl ops::DivAssign<f64> for Vec3 {
+    fn div_assign(&mut self, rhs: f64) {
+        *self = *self / rhs;
+    }
+}
 impl ops::Neg for Vec3 {
     type Output = Vec3;
     fn neg(self) -> Vec3 {
         Vec3::new(-self.x, -self.y, -self.z)
     }
 }
+impl ops::Index<usize> for Vec3 {
+    type Output = f64;
+    fn index(&self, i: usize) -> &f64 {
+        match i {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 index {i} out of range"),
+        }
+    }
+}
+impl ops::IndexMut<usize> for Vec3 {
+    fn index_mut(&mut self, i: usize) -> &mut f64 {
+        match i {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("Vec3 index {i} out of range"),
+        }
+    }
+}
+impl std::iter::Sum for Vec3 {
+    fn sum<I: Iterator<Item = Vec3>>(iter: I) -> Vec3 {
+        iter.fold(Vec3::zero(), |acc, v| acc + v)
+    }
+}
+
+impl ops::Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        Vec3::new(self.row(0).dot(&rhs), self.row(1).dot(&rhs), self.row(2).dot(&rhs))
+    }
+}
+impl ops::Mul<Mat3> for Mat3 {
+    type Output = Mat3;
+    /// Transform applying rhs first, then self
+    fn mul(self, rhs: Mat3) -> Mat3 {
+        Mat3::from_columns(self * rhs.column(0), self * rhs.column(1), self * rhs.column(2))
+    }
+}
+impl ops::Mul<Mat3> for f64 {
+    type Output = Mat3;
+    fn mul(self, rhs: Mat3) -> Mat3 {
+        Mat3::new(rhs.m.map(|row| row.map(|x| self * x)))
+    }
+}
+impl ops::Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+    /// Transform applying rhs first, then self
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Mat4::new(m)
+    }
+}
+
+impl ops::Add<Quaternion> for Quaternion {
+    type Output = Quaternion;
+    fn add(self, rhs: Quaternion) -> Quaternion {
+        Quaternion::new(self.w + rhs.w, self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+impl ops::Mul<Quaternion> for f64 {
+    type Output = Quaternion;
+    fn mul(self, rhs: Quaternion) -> Quaternion {
+        Quaternion::new(self * rhs.w, self * rhs.x, self * rhs.y, self * rhs.z)
+    }
+}
+/// Hamilton product, the rotation applying rhs first, then self
+impl ops::Mul<Quaternion> for Quaternion {
+    type Output = Quaternion;
+    fn mul(self, rhs: Quaternion) -> Quaternion {
+        let (a, b) = (self.vector(), rhs.vector());
+        let v = self.w * b + rhs.w * a + a.cross(&b);
+        Quaternion::new(self.w * rhs.w - a.dot(&b), v.x, v.y, v.z)
+    }
+}
+
 #[test]
 fn test_add() {
     let x = Vec3::new(1.0, 2.0, 3.0);
@@ -156,5 +515,121 @@ fn test_near_zero() {
     let x = Vec3::new(1e-9, 1e-9, 1e-9);
     let y = Vec3::new(1.0, 1e-10, 2.0);
     assert!(x.near_zero());
-    assert_ne!(y.near_zero(), true);
+    assert!(!y.near_zero());
+}
+
+#[test]
+fn test_vec3_ops() {
+    let x = Vec3::new(1.0, 2.0, 3.0);
+    let y = Vec3::new(2.0, 4.0, -1.0);
+    assert_eq!(x - y, Vec3::new(-1.0, -2.0, 4.0));
+    assert_eq!(x * 2.0, 2.0 * x);
+    assert_eq!(x * y, Vec3::new(2.0, 8.0, -3.0));
+    assert_eq!(x / 2.0, Vec3::new(0.5, 1.0, 1.5));
+    assert_eq!(-x, Vec3::new(-1.0, -2.0, -3.0));
+    assert_eq!(x.dot(&y), 7.0);
+    assert_eq!(x.cross(&y), Vec3::new(-14.0, 7.0, 0.0));
+    assert_eq!(vec![x, y, x].into_iter().sum::<Vec3>(), Vec3::new(4.0, 8.0, 5.0));
+}
+
+#[test]
+fn test_vec3_assign_ops() {
+    let mut v = Vec3::new(1.0, 2.0, 3.0);
+    v += Vec3::new(1.0, 1.0, 1.0);
+    assert_eq!(v, Vec3::new(2.0, 3.0, 4.0));
+    v -= Vec3::new(2.0, 0.0, 0.0);
+    assert_eq!(v, Vec3::new(0.0, 3.0, 4.0));
+    v *= 2.0;
+    assert_eq!(v, Vec3::new(0.0, 6.0, 8.0));
+    v /= 4.0;
+    assert_eq!(v, Vec3::new(0.0, 1.5, 2.0));
+}
+
+#[test]
+fn test_vec3_index() {
+    let mut v = Vec3::new(1.0, 2.0, 3.0);
+    assert_eq!([v[0], v[1], v[2]], [1.0, 2.0, 3.0]);
+    v[1] = 5.0;
+    assert_eq!(v.y, 5.0);
+    assert_eq!(v[2], v.axis(2));
+}
+
+#[test]
+#[should_panic]
+fn test_vec3_index_out_of_range() {
+    let _ = Vec3::zero()[3];
+}
+
+#[test]
+fn test_onb() {
+    let onb = Onb::new(&Vec3::new(1.0, 2.0, -2.0));
+    assert!((onb.w - Vec3::new(1.0, 2.0, -2.0) / 3.0).near_zero());
+    assert!(onb.u.dot(&onb.v).abs() < 1e-12 && onb.u.dot(&onb.w).abs() < 1e-12);
+    assert!((onb.u.cross(&onb.v) - onb.w).near_zero());
+    let v = Vec3::new(0.3, -1.0, 2.0);
+    assert!((onb.to_local(onb.transform(v)) - v).near_zero());
+}
+
+#[test]
+fn test_mat3() {
+    let m = Mat3::new([[2.0, 0.0, 1.0], [1.0, 3.0, 0.0], [0.0, 1.0, 4.0]]);
+    assert_eq!(m.determinant(), 25.0);
+    assert_eq!(m.transpose().row(0), m.column(0));
+    assert_eq!(m.transpose().transpose(), m);
+    assert_eq!(m * Vec3::new(1.0, 1.0, 1.0), Vec3::new(3.0, 4.0, 5.0));
+    assert_eq!(Mat3::identity() * m, m);
+    assert_eq!((2.0 * m).m[0][0], 4.0);
+    let product = m * m.inverse().unwrap();
+    for i in 0..3 {
+        assert!((product.row(i) - Mat3::identity().row(i)).near_zero());
+    }
+    let singular = Mat3::from_columns(Vec3::new(1.0, 2.0, 3.0), Vec3::new(2.0, 4.0, 6.0), Vec3::zero());
+    assert!(singular.inverse().is_none());
+}
+
+#[test]
+fn test_mat4() {
+    let p = Point3::new(1.0, 2.0, 3.0);
+    let translate = Mat4::translation(Vec3::new(1.0, 0.0, -1.0));
+    assert_eq!(translate.transform_point(p), Point3::new(2.0, 2.0, 2.0));
+    assert_eq!(translate.transform_vector(p), p);
+    let scale = Mat4::scale(Vec3::new(2.0, 3.0, 4.0));
+    assert_eq!(scale.transform_point(p), Point3::new(2.0, 6.0, 12.0));
+    let rotate = Mat4::rotation(&Vec3::new(0.0, 0.0, 1.0), 90.0);
+    assert!((rotate.transform_vector(Vec3::new(1.0, 0.0, 0.0)) - Vec3::new(0.0, 1.0, 0.0)).near_zero());
+
+    // products apply the right hand side first
+    let m = translate * rotate * scale;
+    let expected = translate.transform_point(rotate.transform_point(scale.transform_point(p)));
+    assert!((m.transform_point(p) - expected).near_zero());
+    assert!((m.inverse().unwrap().transform_point(expected) - p).near_zero());
+    assert_eq!(m.transpose().transpose(), m);
+    assert_eq!(m.transpose().m[3][0], m.m[0][3]);
+    assert_eq!(m.linear().determinant(), (rotate * scale).linear().determinant());
+    assert_eq!(Mat4::identity() * m, m);
+    assert!(Mat4::scale(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+}
+
+#[test]
+fn test_quaternion() {
+    let axis = Vec3::new(1.0, 1.0, 0.0);
+    let q = Quaternion::from_axis_angle(&axis, 60.0);
+    assert!((q.length() - 1.0).abs() < 1e-12);
+    let v = Vec3::new(0.5, -2.0, 1.0);
+    let rotated = q.rotate(v);
+    assert!((rotated - q.to_mat3() * v).near_zero());
+    assert!((rotated - Mat4::rotation(&axis, 60.0).transform_vector(v)).near_zero());
+    assert!((q.conjugate().rotate(rotated) - v).near_zero());
+
+    // composition and interpolation of rotations about one axis add angles
+    let half = Quaternion::from_axis_angle(&axis, 30.0);
+    let composed = half * half;
+    assert!((composed.dot(&q) - 1.0).abs() < 1e-12);
+    let slerped = Quaternion::identity().slerp(&q, 0.5);
+    assert!((slerped.dot(&half) - 1.0).abs() < 1e-12);
+    // the shorter arc is taken when the quaternions are in opposite hemispheres
+    let slerped = Quaternion::identity().slerp(&(-1.0 * q), 0.5);
+    assert!((slerped.dot(&half).abs() - 1.0).abs() < 1e-12);
+    assert_eq!(Quaternion::identity().slerp(&q, 1.0).rotate(v), rotated);
+    assert!(((2.0 * q + q).unit_quaternion().dot(&q) - 1.0).abs() < 1e-12);
 }